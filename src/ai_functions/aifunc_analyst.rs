@@ -0,0 +1,23 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_clarifying_questions(_user_request: &str) {
+    /// INPUT: Takes in a USER_REQUEST for a website and the PROJECT_DESCRIPTION summarized from it
+    /// FUNCTION: Acts as a business analyst who spots ambiguities that would change how the website is built, i.e.:
+    ///   1. data_source: where any data the website shows comes from (user input, a specific external API, ...)
+    ///   2. auth: whether users need accounts, and how they log in
+    ///   3. persistence: what needs to be stored and for how long
+    ///   4. features: which fields, filters or actions each item needs
+    /// IMPORTANT: Only asks about ambiguities that are not already answered by the USER_REQUEST. Asks the most important questions first
+    /// IMPORTANT: Every question has an "assumption" that is the simplest reasonable answer if the user cannot be asked
+    /// OUTPUT: Prints a JSON list of questions in the following format (or an empty list [] if nothing is ambiguous):
+    /// [
+    ///   {
+    ///     "topic": "auth",
+    ///     "question": "Do users need to create an account to save their workouts?",
+    ///     "assumption": "No accounts, all workouts are shared by everyone using the website"
+    ///   },
+    ///   ... // etc
+    /// ]
+    println!(OUTPUT)
+}
@@ -4,28 +4,53 @@ use ai_functions::ai_function;
 pub fn print_project_scope(_project_description: &str) {
     /// Input: Takes in a user request to build a website project description
     /// Function: Converts user request into JSON response of information items required for a website build.
-    /// Important: At least one of the bool results must be true
+    /// Important: At least one of the first three bool results must be true
+    /// Important: Only turns on a requirement if the user request needs it. Everything else is false or "none"
     /// Output: Prints an object response in the following format:
     ///   {
     ///     "is_crud_required": bool, // true if site needs CRUD functionality
     ///     "is_user_login_and_logout": bool // true if site needs users to be able to log in and log out
     ///     "is_external_urls_required": bool // true if site needs to fetch data from third part providers
+    ///     "persistence": "none" | "json_file" | "sqlite" // where data is stored, json_file unless the user asks for a database
+    ///     "realtime": "none" | "websockets" | "sse" // how live updates are pushed to clients
+    ///     "is_pagination_required": bool // true if lists can grow long enough to need pages
+    ///     "is_file_upload_required": bool // true if users upload files
+    ///     "is_scheduled_jobs_required": bool // true if work runs on a schedule (i.e. refreshing prices every hour)
+    ///     "is_rate_limiting_required": bool // true if clients must be limited in how many requests they make
+    ///     "is_admin_roles_required": bool // true if some users are admins with extra permissions
+    ///     "is_i18n_required": bool // true if the site is shown in more than one language
     ///   }
     /// Example 1:
-    ///   user_request = "I need a full stack website that accepts users and gets stock price data"
+    ///   user_request = "I need a full stack website that accepts users and gets stock price data, refreshed every hour"
     ///   prints:
     ///   {
-    ///     "is_crud_required": true
-    ///     "is_user_login_and_logout": true
-    ///     "is_external_urls_required": bool true
+    ///     "is_crud_required": true,
+    ///     "is_user_login_and_logout": true,
+    ///     "is_external_urls_required": true,
+    ///     "persistence": "json_file",
+    ///     "realtime": "none",
+    ///     "is_pagination_required": false,
+    ///     "is_file_upload_required": false,
+    ///     "is_scheduled_jobs_required": true,
+    ///     "is_rate_limiting_required": false,
+    ///     "is_admin_roles_required": false,
+    ///     "is_i18n_required": false
     ///   }
     /// Example 2:
     ///   user_request = "I need a simple TODO app"
     ///   prints:
     ///   {
-    ///     "is_crud_required": true
-    ///     "is_user_login_and_logout": false
-    ///     "is_external_urls_required": bool false
+    ///     "is_crud_required": true,
+    ///     "is_user_login_and_logout": false,
+    ///     "is_external_urls_required": false,
+    ///     "persistence": "json_file",
+    ///     "realtime": "none",
+    ///     "is_pagination_required": false,
+    ///     "is_file_upload_required": false,
+    ///     "is_scheduled_jobs_required": false,
+    ///     "is_rate_limiting_required": false,
+    ///     "is_admin_roles_required": false,
+    ///     "is_i18n_required": false
     ///   }
     println!(OUTPUT)
 }
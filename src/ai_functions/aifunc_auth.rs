@@ -0,0 +1,23 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_code_with_auth(_project_description_and_code: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION and the BACKEND_FILES of a Rust actix-web webserver that stores its data in a JSON file database
+    /// FUNCTION: Adds user authentication to the BACKEND_FILES using exactly this contract:
+    ///   1. POST /register with body {"username": "string", "password": "string"} creates a user and returns 200
+    ///   2. POST /login with the same body returns 200 and {"token": "string"} for a valid username and password, otherwise 401
+    ///   3. POST /logout with the header "Authorization: Bearer <token>" ends the session and returns 200
+    ///   4. Routes that belong to a user require the header "Authorization: Bearer <token>" and return 401 without a valid token
+    /// IMPORTANT: Passwords are NEVER stored in plain text. Users are stored in the JSON file database with a salted hash made with the argon2 crate:
+    ///   use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+    ///   let salt = SaltString::generate(&mut OsRng); let password_hash = Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string();
+    /// IMPORTANT: Session tokens are random, kept in the app state and removed on logout
+    /// IMPORTANT: Keeps all existing functionality and routes. The only new libraries allowed are argon2 and password-hash
+    /// IMPORTANT: If MISSING_AUTH is given, fixes every problem it lists
+    /// OUTPUT: Prints ONLY a JSON list of the files that were changed or added (i.e. src/auth.rs), each with its full new content:
+    /// [
+    ///   { "path": "src/auth.rs", "content": "..." },
+    ///   ... // etc
+    /// ]
+    println!(OUTPUT)
+}
@@ -8,8 +8,18 @@ pub fn print_backend_webserver_code(_project_description_and_template: &str) {
     /// FUNCTION: Takes an existing set of code marked as CODE_TEMPLATE and updates or re-writes it to work for the purpose in the PROJECT_DESCRIPTION
     /// IMPORTANT: The following libraries are already installed
     ///   reqwest, serde, serde_json, tokio, actix-web, async-trait, actix_cors
-    /// No other external libraries should be used. Write functions that fit with the description from the PROJECT_DESCRIPTION
-    /// OUTPUT: Print ONLY the code, nothing else. This function ONLY prints code.
+    /// No other external libraries should be used, except the crates named in SCOPE_FEATURES. Write functions that fit with the description from the PROJECT_DESCRIPTION
+    /// IMPORTANT: Splits the code into files with paths relative to the project root: src/main.rs declares the modules and starts the server, with i.e. src/models.rs, src/handlers.rs and src/db.rs for the rest
+    /// IMPORTANT: The server binds to 127.0.0.1 on the port read from the PORT environment variable, defaulting to 8080
+    /// IMPORTANT: Calls to EXTERNAL_URLS read the base URL of the host (scheme and host) from the environment variable given for it, defaulting to the real base URL, and append the path and query
+    /// IMPORTANT: If EXTERNAL_URL_PROFILES are given, the structs decoding each external response match its schema and sample exactly
+    /// IMPORTANT: If SCOPE_FEATURES are given, builds every feature exactly as described
+    /// OUTPUT: Prints ONLY a JSON list of files in the following format:
+    /// [
+    ///   { "path": "src/main.rs", "content": "mod db;\nmod handlers;\nmod models;\n..." },
+    ///   { "path": "src/models.rs", "content": "use serde::{Deserialize, Serialize};\n..." },
+    ///   ... // etc
+    /// ]
     println!(OUTPUT)
 }
 
@@ -20,16 +30,60 @@ pub fn print_improved_webserver_code(_project_description_and_template: &str) {
     ///   1. Removes any bugs in the code and adds minor additional functionality
     ///   2. Makes sure everything requested in the spec from a backend standpoint was followed. If not, add the feature. No code should be implemented later. Everything should be written now.
     ///   3. ONLY writes the code. No commentary.
-    /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template
+    /// IMPORTANT: The following libraries are already installed. Does not use ANY libraries other than what was provided in the template and the crates named in SCOPE_FEATURES
     ///   reqwest, serde, serde_json, tokio, actix-web, async-trait
+    /// IMPORTANT: The CODE TEMPLATE is given as BACKEND_FILES. New files can be added, with paths relative to the project root
+    /// IMPORTANT: The server binds to 127.0.0.1 on the port read from the PORT environment variable, defaulting to 8080
+    /// IMPORTANT: Calls to EXTERNAL_URLS read the base URL of the host (scheme and host) from the environment variable given for it, defaulting to the real base URL, and append the path and query
+    /// IMPORTANT: If EXTERNAL_URL_PROFILES are given, the structs decoding each external response match its schema and sample exactly
+    /// IMPORTANT: If SCOPE_FEATURES are given, builds every feature exactly as described
+    /// OUTPUT: Prints ONLY a JSON list of the files that were changed or added, each with its full new content:
+    /// [
+    ///   { "path": "src/handlers.rs", "content": "..." },
+    ///   ... // etc
+    /// ]
     println!(OUTPUT)
 }
 
 #[ai_function]
 pub fn print_fixed_code(_broken_code_with_bugs: &str) {
-    /// INPUT: Takes in Rust BROKEN_CODE and the ERROR_BUGS found
-    /// FUNCTION: Removes bugs from code
-    /// IMPORTANT: Only prints out the new and improved code. No commentary or anything else
+    /// INPUT: Takes in the Rust BROKEN_FILES named in the ERROR_BUGS found, and the paths of every OTHER_FILES in the project
+    /// FUNCTION: Removes bugs from the BROKEN_FILES
+    /// IMPORTANT: Only prints a JSON list of the fixed files, each with its full new content. No commentary or anything else:
+    /// [
+    ///   { "path": "src/handlers.rs", "content": "..." },
+    ///   ... // etc
+    /// ]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_code_fix_edits(_broken_code_with_bugs: &str) {
+    /// INPUT: Takes in the Rust BROKEN_FILES named in the ERROR_BUGS found, and the paths of every OTHER_FILES in the project
+    /// FUNCTION: Removes bugs from the BROKEN_FILES with the smallest search/replace edits possible, leaving working code untouched
+    /// IMPORTANT: "search" is copied exactly from the file and matches exactly one place in it. Include a few surrounding lines if needed to make it unique
+    /// IMPORTANT: Only prints a JSON list of edits. No commentary or anything else:
+    /// [
+    ///   {
+    ///     "path": "src/handlers.rs",
+    ///     "search": "let id = path.into_inner();\n    let item = db.items.get(id).unwrap();",
+    ///     "replace": "let id = path.into_inner();\n    let item = match db.items.get(&id) {\n        Some(item) => item,\n        None => return HttpResponse::NotFound().finish(),\n    };"
+    ///   },
+    ///   ... // etc
+    /// ]
+    println!(OUTPUT)
+}
+
+#[ai_function]
+pub fn print_code_addressing_review(_code_with_review_comments: &str) {
+    /// INPUT: Takes in Rust BACKEND_FILES and the REVIEW_COMMENTS left by a code reviewer
+    /// FUNCTION: Updates the code so that every review comment is addressed, starting with the most severe
+    /// IMPORTANT: Keeps all existing functionality and routes. Does not use any libraries that are not already used in the code
+    /// IMPORTANT: Only prints a JSON list of the files that were changed, each with its full new content. No commentary or anything else:
+    /// [
+    ///   { "path": "src/handlers.rs", "content": "..." },
+    ///   ... // etc
+    /// ]
     println!(OUTPUT)
 }
 
@@ -41,8 +95,9 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///   "route": This represents the url path of the endpoint
     ///   "is_route_dynamic": if a route has curly braces in it such as {symbol} or {id} as an example, then this will be set to true
     ///   "method": This represents the method being called
-    ///   "request_body": This represents the body of a post method request
+    ///   "request_body": This represents the JSON body of a post, put or patch request, or "None" if the route reads no body
     ///   "response": This represents the output based upon the structs in the code and understanding the functions
+    ///   "is_protected": if the route can only be called by a logged in user (i.e. it checks an Authorization header or session), then this will be set to true
     /// IMPORTANT: Only prints out the JSON schema. No commentary or anything else.
     /// MUST READ: All keys are strings. Even bool should be wrapped in double quotes as "bool"
     /// EXAMPLE:
@@ -79,7 +134,8 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///       "id": "number",
     ///       "name": "string",
     ///       "completed": "bool",
-    ///     }
+    ///     },
+    ///     "is_protected": "false"
     ///   },
     ///   {
     ///     "route": "/item",
@@ -90,21 +146,39 @@ pub fn print_rest_api_endpoints(_code_input: &str) {
     ///       "name": "string",
     ///       "completed": "bool",
     ///     },
-    ///     "response": "None"
+    ///     "response": "None",
+    ///     "is_protected": "false"
+    ///   },
+    ///   {
+    ///     "route": "/item/{id}",
+    ///     "is_route_dynamic": "true",
+    ///     "method": "put",
+    ///     "request_body": {
+    ///       "name": "string",
+    ///       "completed": "bool",
+    ///     },
+    ///     "response": {
+    ///       "id": "number",
+    ///       "name": "string",
+    ///       "completed": "bool",
+    ///     },
+    ///     "is_protected": "false"
     ///   },
     ///   {
     ///     "route": "/item/{id}",
     ///     "is_route_dynamic": "true",
     ///     "method": "delete",
     ///     "request_body": "None",
-    ///     "response": "None"
+    ///     "response": "None",
+    ///     "is_protected": "false"
     ///   },
     ///   {
     ///     "route": "/crypto",
     ///     "is_route_dynamic": "false",
     ///     "method": "get",
     ///     "request_body": "None",
-    ///     "response": "not_provided"
+    ///     "response": "not_provided",
+    ///     "is_protected": "false"
     ///   },
     ///   ... // etc
     /// ]
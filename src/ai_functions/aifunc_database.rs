@@ -0,0 +1,36 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_data_model(_project_description_and_scope: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION and whether CRUD is required (IS_CRUD_REQUIRED) for a website backend
+    /// FUNCTION: Designs the data model that the backend stores in its JSON file database
+    /// IMPORTANT: Every entity has an "id" field of type "u64". Field types are Rust types using only std types (u64, i64, f64, bool, String, Vec<..>, Option<..>)
+    /// IMPORTANT: Entity names are PascalCase and field names are snake_case. Relations refer to other entities by name and point at the field holding the related id
+    /// IMPORTANT: If IS_CRUD_REQUIRED is true, includes every entity users need to create, read, update and delete
+    /// OUTPUT: Prints a JSON object in the following format:
+    /// {
+    ///   "entities": [
+    ///     {
+    ///       "name": "User",
+    ///       "fields": [
+    ///         { "name": "id", "field_type": "u64" },
+    ///         { "name": "username", "field_type": "String" }
+    ///       ],
+    ///       "relations": []
+    ///     },
+    ///     {
+    ///       "name": "Workout",
+    ///       "fields": [
+    ///         { "name": "id", "field_type": "u64" },
+    ///         { "name": "user_id", "field_type": "u64" },
+    ///         { "name": "duration_minutes", "field_type": "u32" },
+    ///         { "name": "notes", "field_type": "Option<String>" }
+    ///       ],
+    ///       "relations": [
+    ///         { "kind": "many_to_one", "entity": "User", "via_field": "user_id" }
+    ///       ]
+    ///     }
+    ///   ]
+    /// }
+    println!(OUTPUT)
+}
@@ -0,0 +1,12 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_readme_overview(_project_description_and_env_vars: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION of a Rust actix-web webserver and the ENV_VARS its code reads
+    /// FUNCTION: Writes the start of a README.md for the webserver project for a developer who has never seen it
+    /// IMPORTANT: Includes a "# <Project Name>" title, a "## Purpose" section and a "## How to Run" section
+    /// IMPORTANT: How to Run explains setting the ENV_VARS, running "cargo run" and that the server listens on the port in the PORT environment variable (http://localhost:8080 by default)
+    /// IMPORTANT: Does NOT list the API endpoints or environment variables in a table, these sections are added separately
+    /// OUTPUT: Prints ONLY the markdown. No code fences around the whole document.
+    println!(OUTPUT)
+}
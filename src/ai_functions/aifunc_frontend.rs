@@ -0,0 +1,18 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_frontend_files(_project_description_and_api_schema: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION and the API_ENDPOINT_SCHEMA of a Rust actix-web backend
+    /// FUNCTION: Writes a static frontend (HTML, CSS and plain JavaScript) that lets users use every route in the API_ENDPOINT_SCHEMA
+    /// IMPORTANT: The pages are served by the same web server as the API, so every fetch call uses a relative URL such as fetch("/item")
+    /// IMPORTANT: Only calls routes that exist in the API_ENDPOINT_SCHEMA. Dynamic segments such as {id} are filled with template literals such as fetch(`/item/${id}`)
+    /// IMPORTANT: No frameworks, build tools or CDN links. The entry page must be named index.html
+    /// IMPORTANT: If FETCH_ERRORS are provided, fixes every fetch call listed in them
+    /// OUTPUT: Prints a JSON list of files with paths relative to the static directory in the following format:
+    /// [
+    ///   { "path": "index.html", "content": "<!DOCTYPE html>..." },
+    ///   { "path": "styles.css", "content": "body { ... }" },
+    ///   { "path": "app.js", "content": "async function loadItems() { const res = await fetch(\"/item\"); ... }" }
+    /// ]
+    println!(OUTPUT)
+}
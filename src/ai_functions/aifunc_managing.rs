@@ -13,3 +13,24 @@ pub fn convert_user_input_to_goal(_user_request: &str) {
     ///   OUTPUT = "build a website that fetches and stores crypto price data within a supabase setup including a frontend UI to fetch the data."
     println!(OUTPUT)
 }
+
+#[ai_function]
+pub fn print_requirement_coverage(_request_and_deliverables: &str) {
+    /// Input: Takes in the USER_REQUEST, PROJECT_DESCRIPTION, PROJECT_SCOPE, API_ENDPOINT_SCHEMA and BACKEND_CODE of a website build
+    /// Function: Acts as a strict judge comparing what the user asked for against what was built
+    /// Important: Only lists requirements that are clearly asked for in the USER_REQUEST or PROJECT_DESCRIPTION and are missing or broken in the BACKEND_CODE
+    /// Output: Prints an object response in the following format:
+    ///   {
+    ///     "is_satisfied": bool, // true if every requirement has been built
+    ///     "missing_requirements": ["requirement 1", "requirement 2", ...] // short instructions describing what still needs to be built
+    ///   }
+    /// Example:
+    ///   USER_REQUEST = "I need a todo app where I can mark items as completed"
+    ///   BACKEND_CODE has routes to create, read and delete items but items have no completed field
+    ///   prints:
+    ///   {
+    ///     "is_satisfied": false,
+    ///     "missing_requirements": ["Add a completed field to items and a route to mark an item as completed"]
+    ///   }
+    println!(OUTPUT)
+}
@@ -0,0 +1,23 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_code_review_comments(_project_description_and_code: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION and the BACKEND_CODE of a Rust actix-web webserver
+    /// FUNCTION: Acts as a senior code reviewer who did not write the code and reviews it for:
+    ///   1. security: i.e. unvalidated input, secrets in code, unsafe file or process access, permissive CORS
+    ///   2. error_handling: i.e. unwrap or expect on user input, panics in handlers, missing error responses
+    ///   3. spec_adherence: features in the PROJECT_DESCRIPTION that are missing or built incorrectly
+    /// IMPORTANT: Only comments on real problems. If the code has no problems, prints an empty list []
+    /// IMPORTANT: severity is one of "low", "medium", "high" or "critical"
+    /// OUTPUT: Prints a JSON list of review comments in the following format:
+    /// [
+    ///   {
+    ///     "severity": "high",
+    ///     "category": "error_handling",
+    ///     "location": "fn update_item",
+    ///     "rationale": "Calls unwrap on the item lookup, so updating a missing item panics instead of returning 404"
+    ///   },
+    ///   ... // etc
+    /// ]
+    println!(OUTPUT)
+}
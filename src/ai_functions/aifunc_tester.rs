@@ -0,0 +1,17 @@
+use ai_functions::ai_function;
+
+#[ai_function]
+pub fn print_integration_tests(_project_description_and_schema: &str) {
+    /// INPUT: Takes in a PROJECT_DESCRIPTION, the API_ENDPOINT_SCHEMA and DATA_MODEL of a Rust actix-web webserver and the DEPENDENCIES from its Cargo.toml
+    /// FUNCTION: Writes a Rust integration test file (tests/api_tests.rs) that calls the running webserver
+    /// IMPORTANT: Reads the address of the webserver from the API_BASE_URL environment variable, defaulting to http://localhost:8080
+    /// IMPORTANT: For every resource, tests creating an item, reading it back, updating it, reading the update, deleting it and checking it is gone
+    /// IMPORTANT: Asserts the status code AND the response body of every request, using the request_body and response shapes in the API_ENDPOINT_SCHEMA
+    /// IMPORTANT: For routes with "is_protected" true, first registers a unique user with POST /register, logs in with POST /login to get {"token": ...} and sends the header "Authorization: Bearer <token>"
+    /// IMPORTANT: Tests run in parallel, so every test creates its own items and never relies on items created by another test
+    /// IMPORTANT: If SCOPE_FEATURES are given, adds the tests described for each feature
+    /// IMPORTANT: Only uses crates listed in DEPENDENCIES (i.e. reqwest, serde_json and tokio with #[tokio::test])
+    /// IMPORTANT: If PREVIOUS_TESTS and TEST_COMPILE_ERRORS are given, fixes the errors in the previous tests
+    /// OUTPUT: Prints ONLY the Rust code of the test file. THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE.
+    println!(OUTPUT)
+}
@@ -1,3 +1,10 @@
+pub mod aifunc_analyst;
 pub mod aifunc_architect;
+pub mod aifunc_auth;
 pub mod aifunc_backend;
+pub mod aifunc_database;
+pub mod aifunc_docs;
+pub mod aifunc_frontend;
 pub mod aifunc_managing;
+pub mod aifunc_reviewer;
+pub mod aifunc_tester;
@@ -1,8 +1,10 @@
+use crate::models::agents::agent_traits::AgentError;
 use crate::models::general::llm::{APIResponse, ChatCompletion, Message};
 use dotenv::dotenv;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
 use std::env;
+use tokio_util::sync::CancellationToken;
 
 const CHAT_GPT_MODEL: &str = "gpt-4";
 
@@ -10,8 +12,17 @@ const CHAT_GPT_MODEL: &str = "gpt-4";
 /// # Arguments
 ///
 /// * `messages` - A list of messages to pass into the LLM model
+/// * `cancel` - Stops the request if the run is cancelled, returning AgentError::Cancelled
 ///
-pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::error::Error + Send>> {
+pub async fn call_gpt(
+    messages: Vec<Message>,
+    cancel: &CancellationToken,
+) -> Result<String, Box<dyn std::error::Error + Send>> {
+    // Do not start a request once the run has been cancelled
+    if cancel.is_cancelled() {
+        return Err(Box::new(AgentError::Cancelled));
+    }
+
     dotenv().ok();
 
     // Extract API keys
@@ -53,25 +64,33 @@ pub async fn call_gpt(messages: Vec<Message>) -> Result<String, Box<dyn std::err
         temperature: 0.1,
     };
 
-    // Troubleshooting and debugging
-    let res_raw = client
-        .post(url)
-        .json(&chat_completion)
-        .send()
-        .await
-        .unwrap();
-    dbg!(res_raw.text().await.unwrap());
-
-    // Extract API Response
-    let res: APIResponse = client
-        .post(url)
-        .json(&chat_completion)
-        .send()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
-        .json()
-        .await
-        .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?;
+    let request = async {
+        // Troubleshooting and debugging
+        let res_raw = client
+            .post(url)
+            .json(&chat_completion)
+            .send()
+            .await
+            .unwrap();
+        dbg!(res_raw.text().await.unwrap());
+
+        // Extract API Response
+        client
+            .post(url)
+            .json(&chat_completion)
+            .send()
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })?
+            .json::<APIResponse>()
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error + Send> { Box::new(e) })
+    };
+
+    // Stop waiting on the API as soon as the run is cancelled
+    let res: APIResponse = tokio::select! {
+        res = request => res?,
+        _ = cancel.cancelled() => return Err(Box::new(AgentError::Cancelled)),
+    };
 
     // Send Response
     Ok(res.choices[0].message.content.clone())
@@ -90,15 +109,28 @@ mod tests {
 
         let messages: Vec<Message> = vec![message];
 
-        let res: Result<String, Box<dyn std::error::Error + Send>> = call_gpt(messages).await;
-        match res {
-            Ok(res_str) => {
-                dbg!(res_str);
-                assert!(true);
-            }
-            Err(_) => {
-                assert!(false);
-            }
-        }
+        let res: Result<String, Box<dyn std::error::Error + Send>> =
+            call_gpt(messages, &CancellationToken::new()).await;
+        let res_str: String = res.unwrap_or_else(|e| panic!("Call to OpenAI failed: {}", e));
+        dbg!(res_str);
+    }
+
+    #[tokio::test]
+    async fn tests_call_to_openai_cancelled() {
+        let message: Message = Message {
+            role: "user".to_string(),
+            content: "This request is cancelled before it is sent.".to_string(),
+        };
+
+        let cancel: CancellationToken = CancellationToken::new();
+        cancel.cancel();
+
+        let err: Box<dyn std::error::Error + Send> = call_gpt(vec![message], &cancel)
+            .await
+            .expect_err("Cancelled call should not return a response");
+        assert_eq!(
+            err.downcast_ref::<AgentError>(),
+            Some(&AgentError::Cancelled)
+        );
     }
 }
@@ -1,8 +1,12 @@
+use crate::helpers::sandbox::SandboxMode;
+use crate::models::agents::agent_traits::AgentError;
 use crossterm::{
     style::{Color, ResetColor, SetForegroundColor},
     ExecutableCommand,
 };
+use std::env;
 use std::io::{stdin, stdout};
+use tokio_util::sync::CancellationToken;
 
 /// Enum to describe the different commands we can receive and print
 #[derive(PartialEq, Debug)]
@@ -40,12 +44,39 @@ impl PrintCommand {
     }
 }
 
+/// Read a line from stdin without blocking the runtime, so Ctrl-C can cancel the run while waiting on the user
+/// Returns AgentError::Cancelled if the run is cancelled before the user answers
+async fn read_user_line(cancel: &CancellationToken) -> Result<String, AgentError> {
+    // The read cannot be interrupted once started, so do not start one after the run is cancelled
+    if cancel.is_cancelled() {
+        return Err(AgentError::Cancelled);
+    }
+
+    let read_line = tokio::task::spawn_blocking(|| {
+        let mut user_response: String = String::new();
+        stdin()
+            .read_line(&mut user_response)
+            .expect("Failed to read response");
+        user_response
+    });
+
+    tokio::select! {
+        user_response = read_line => Ok(user_response.expect("Failed to read response")),
+        _ = cancel.cancelled() => Err(AgentError::Cancelled),
+    }
+}
+
 /// Prompt a user with a question that will expect a response
 /// This will be used when we ask the user what they want to build
+/// Returns AgentError::Cancelled if the run is cancelled before the user answers
 /// # Arguments
 ///
 /// * `question` - The question to ask the user
-pub fn get_user_response(question: &str) -> String {
+/// * `cancel` - Stops waiting on the user if the run is cancelled
+pub async fn get_user_response(
+    question: &str,
+    cancel: &CancellationToken,
+) -> Result<String, AgentError> {
     let mut stdout: std::io::Stdout = stdout();
 
     // Print the question in a specific color
@@ -57,58 +88,250 @@ pub fn get_user_response(question: &str) -> String {
     stdout.execute(ResetColor).unwrap();
 
     // Read user input
-    let mut user_response: String = String::new();
-    stdin()
-        .read_line(&mut user_response)
-        .expect("Failed to read response");
+    let user_response: String = read_user_line(cancel).await?;
 
     // Trim whitespace and return the user response
-    return user_response.trim().to_string();
+    return Ok(user_response.trim().to_string());
 }
 
-/// Check with the user and ensure they are giving the final approval before running any code
-/// This is a safety check to ensure the user is aware they are running code generated by AI
-pub fn confirm_safe_code() -> bool {
+/// Points in a run where a human can review the work of the agents before it continues
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ApprovalGate {
+    /// After the user request has been converted into a project goal
+    ProjectGoal,
+    /// After the Solutions Architect has determined the project scope
+    ProjectScope,
+    /// After the Solutions Architect has discovered external URLs
+    ExternalUrls,
+    /// Before each attempt to fix bugs in the backend code
+    FixIteration,
+    /// Before running any code written by AI
+    RunCode,
+}
+
+/// Implementation of ApprovalGate
+impl ApprovalGate {
+    /// Key used to enable the gate through the APPROVAL_GATES environment variable
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::ProjectGoal => "goal",
+            Self::ProjectScope => "scope",
+            Self::ExternalUrls => "urls",
+            Self::FixIteration => "fix",
+            Self::RunCode => "run",
+        }
+    }
+
+    /// Instructions shown to the user when they choose to edit at this gate
+    fn edit_instructions(&self) -> &'static str {
+        match self {
+            Self::ProjectGoal => "Enter the new project goal:",
+            Self::ProjectScope => {
+                "Enter the scope flags to toggle separated by commas (crud, login, urls, pagination, uploads, jobs, ratelimit, admin, i18n) or set persistence=none|json_file|sqlite and realtime=none|websockets|sse:"
+            }
+            Self::ExternalUrls => {
+                "Enter URLs to add as +url and URLs to remove as -url, separated by spaces:"
+            }
+            Self::FixIteration => "Enter any guidance to send along with the bugs:",
+            Self::RunCode => "Edit the code on disk, then press Enter to review it again:",
+        }
+    }
+}
+
+/// The decision a human (or the auto-approve policy) makes at an approval gate
+#[derive(PartialEq, Debug, Clone)]
+pub enum ApprovalDecision {
+    /// Continue with the work as it is
+    Approve,
+    /// Continue with the work after applying the user's edit
+    Edit(String),
+    /// Throw the work away and ask the agent to produce it again
+    Regenerate,
+    /// Stop the project
+    Abort,
+}
+
+/// Describes how approval gates are resolved
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ApprovalPolicy {
+    /// Ask the user at every enabled gate
+    Interactive,
+    /// Approve every gate without asking (for trusted batch runs)
+    AutoApprove,
+}
+
+/// Implementation of ApprovalPolicy
+impl ApprovalPolicy {
+    /// Read the policy from the APPROVAL_POLICY environment variable ("interactive" or "auto")
+    pub fn from_env() -> Self {
+        match env::var("APPROVAL_POLICY") {
+            Ok(policy) if policy.trim().eq_ignore_ascii_case("auto") => Self::AutoApprove,
+            _ => Self::Interactive,
+        }
+    }
+}
+
+/// How a gate is resolved before anything is asked
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum GateResolution {
+    /// The gate is not enabled, so the work continues without asking
+    Skip,
+    /// The auto-approve policy approves the gate without asking
+    AutoApprove,
+    /// The user reviews the work
+    Prompt,
+}
+
+/// Decide how a gate is resolved from the enabled gates and the approval policy
+/// The enabled gates come from APPROVAL_GATES, a comma separated list of gate keys, "all" or "none"
+/// The gate before running code is always enabled, whatever APPROVAL_GATES says
+fn resolve_gate(gate: ApprovalGate, gates: &str, policy: ApprovalPolicy) -> GateResolution {
+    if gate != ApprovalGate::RunCode && !gates_contain(gates, gate) {
+        return GateResolution::Skip;
+    }
+    match policy {
+        ApprovalPolicy::AutoApprove => GateResolution::AutoApprove,
+        ApprovalPolicy::Interactive => GateResolution::Prompt,
+    }
+}
+
+/// Check whether a comma separated list of gate keys contains a gate
+fn gates_contain(gates: &str, gate: ApprovalGate) -> bool {
+    gates
+        .split(',')
+        .map(|key| key.trim().to_lowercase())
+        .any(|key| key == "all" || key == gate.key())
+}
+
+/// Ask the user to review the work of an agent at an approval gate
+/// Gates that are not enabled, or any gate under the auto-approve policy, are approved without asking
+/// Approving the gate before running code without asking prints a warning
+/// Returns AgentError::Cancelled if the run is cancelled before the user decides
+/// # Arguments
+///
+/// * `gate` - The gate the run has reached
+/// * `agent_pos` - The agent whose work is being reviewed
+/// * `summary` - The work to show the user
+/// * `cancel` - Stops waiting on the user if the run is cancelled
+pub async fn request_approval(
+    gate: ApprovalGate,
+    agent_pos: &str,
+    summary: &str,
+    cancel: &CancellationToken,
+) -> Result<ApprovalDecision, AgentError> {
+    let gates: String = env::var("APPROVAL_GATES").unwrap_or_default();
+    match resolve_gate(gate, &gates, ApprovalPolicy::from_env()) {
+        GateResolution::Skip => return Ok(ApprovalDecision::Approve),
+        GateResolution::AutoApprove if gate == ApprovalGate::RunCode => {
+            PrintCommand::Issue.print_agent_message(
+                agent_pos,
+                "WARNING: Running code written by AI without confirmation because APPROVAL_POLICY=auto",
+            );
+            return Ok(ApprovalDecision::Approve);
+        }
+        GateResolution::AutoApprove => {
+            let auto_msg: String = format!("Auto-approving '{}' gate", gate.key());
+            PrintCommand::UnitTest.print_agent_message(agent_pos, auto_msg.as_str());
+            return Ok(ApprovalDecision::Approve);
+        }
+        GateResolution::Prompt => {}
+    }
+
     let mut stdout: std::io::Stdout = stdout();
     loop {
-        // Print the question in specified color
+        // Print the work to review in specified color
         stdout.execute(SetForegroundColor(Color::Blue)).unwrap();
-        println!("");
-        print!("WARNING: You are about to run code written entirely by AI. ");
-        println!("Review your code and confirm you wish to continue.");
+        println!();
+        println!("Approval required ({}) from {}:", gate.key(), agent_pos);
+        println!("{}", summary);
 
         // Reset Color
         stdout.execute(ResetColor).unwrap();
 
         // Present Options with different colors
         stdout.execute(SetForegroundColor(Color::Green)).unwrap();
-        println!("[1] All good");
+        println!("[1] Approve");
+        stdout.execute(SetForegroundColor(Color::Yellow)).unwrap();
+        println!("[2] Edit");
+        println!("[3] Regenerate");
         stdout.execute(SetForegroundColor(Color::DarkRed)).unwrap();
-        println!("[2] Lets stop this project");
+        println!("[4] Abort the project");
 
         // Reset Color
         stdout.execute(ResetColor).unwrap();
 
         // Read user input
-        let mut human_response: String = String::new();
-        stdin()
-            .read_line(&mut human_response)
-            .expect("Failed to read response");
+        let human_response: String = read_user_line(cancel).await?;
 
         // Trim whitespace and convert to lowercase
         let human_response: String = human_response.trim().to_lowercase();
 
         // Match response
         match human_response.as_str() {
-            "1" | "ok" | "y" => return true,
-            "2" | "no" | "n" => return false,
+            "1" | "ok" | "y" => return Ok(ApprovalDecision::Approve),
+            "2" | "e" => {
+                let edit: String = get_user_response(gate.edit_instructions(), cancel).await?;
+                return Ok(ApprovalDecision::Edit(edit));
+            }
+            "3" | "r" => return Ok(ApprovalDecision::Regenerate),
+            "4" | "no" | "n" => return Ok(ApprovalDecision::Abort),
             _ => {
-                println!("Invalid input. Please select '1' or '2'")
+                println!("Invalid input. Please select '1', '2', '3' or '4'")
             }
         }
     }
 }
 
+/// Check with the user and ensure they are giving the final approval before running any code
+/// This is a safety check to ensure the user is aware they are running code generated by AI
+/// # Arguments
+///
+/// * `agent_pos` - The agent that wrote the code
+/// * `findings` - Findings from the security scan of the code, one per line
+/// * `cancel` - Stops waiting on the user if the run is cancelled
+pub async fn confirm_safe_code(
+    agent_pos: &str,
+    findings: &str,
+    cancel: &CancellationToken,
+) -> Result<ApprovalDecision, AgentError> {
+    // The sandbox keeps the code away from the network and the user's files, so the user can opt out of confirming
+    if SandboxMode::from_env().is_run_confirmation_skipped() {
+        PrintCommand::Issue.print_agent_message(
+            agent_pos,
+            "WARNING: Running code written by AI without confirmation because SANDBOX=trusted",
+        );
+        return Ok(ApprovalDecision::Approve);
+    }
+
+    let mut summary: String = "WARNING: You are about to run code written entirely by AI. Review your code and confirm you wish to continue.".to_string();
+    if !findings.is_empty() {
+        summary.push_str(&format!("\nSecurity scan findings:\n{}", findings));
+    }
+
+    request_approval(ApprovalGate::RunCode, agent_pos, &summary, cancel).await
+}
+
+/// Listen for Ctrl-C and cancel the run gracefully
+/// Prompts stop waiting on the user when the run is cancelled, so the manager can write its checkpoint
+/// A second Ctrl-C exits immediately in case the run is still stuck
+pub fn spawn_ctrl_c_handler(cancel: CancellationToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+
+        PrintCommand::Issue.print_agent_message(
+            "Project Manager",
+            "Ctrl-C received, stopping agents... (press Ctrl-C again to force quit)",
+        );
+        cancel.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +341,62 @@ mod tests {
         PrintCommand::AICall
             .print_agent_message("Managing Agent", "Testing testing, processing something");
     }
+
+    #[test]
+    fn tests_gates_contain() {
+        assert!(gates_contain("run", ApprovalGate::RunCode));
+        assert!(gates_contain("goal, Scope", ApprovalGate::ProjectScope));
+        assert!(gates_contain("all", ApprovalGate::FixIteration));
+        assert!(!gates_contain("none", ApprovalGate::RunCode));
+        assert!(!gates_contain("goal,urls", ApprovalGate::RunCode));
+    }
+
+    #[tokio::test]
+    async fn tests_request_approval_cancelled() {
+        let cancel: CancellationToken = CancellationToken::new();
+        cancel.cancel();
+
+        // The gate before running code always prompts, so the cancelled run stops instead of waiting on the user
+        let decision: Result<ApprovalDecision, AgentError> =
+            request_approval(ApprovalGate::RunCode, "Backend Developer", "code", &cancel).await;
+        assert_eq!(decision, Err(AgentError::Cancelled));
+    }
+
+    #[test]
+    fn tests_resolve_gate() {
+        // Running code is confirmed even when it is left out of the enabled gates
+        assert_eq!(
+            resolve_gate(ApprovalGate::RunCode, "goal", ApprovalPolicy::Interactive),
+            GateResolution::Prompt
+        );
+        assert_eq!(
+            resolve_gate(ApprovalGate::RunCode, "none", ApprovalPolicy::Interactive),
+            GateResolution::Prompt
+        );
+        assert_eq!(
+            resolve_gate(ApprovalGate::RunCode, "goal", ApprovalPolicy::AutoApprove),
+            GateResolution::AutoApprove
+        );
+
+        assert_eq!(
+            resolve_gate(
+                ApprovalGate::ProjectGoal,
+                "goal",
+                ApprovalPolicy::Interactive
+            ),
+            GateResolution::Prompt
+        );
+        assert_eq!(
+            resolve_gate(
+                ApprovalGate::ProjectScope,
+                "goal",
+                ApprovalPolicy::Interactive
+            ),
+            GateResolution::Skip
+        );
+        assert_eq!(
+            resolve_gate(ApprovalGate::ProjectScope, "", ApprovalPolicy::AutoApprove),
+            GateResolution::Skip
+        );
+    }
 }
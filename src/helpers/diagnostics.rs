@@ -0,0 +1,282 @@
+use crate::models::agents::agent_traits::ProjectFile;
+use serde::{Deserialize, Serialize};
+
+/// Number of lines shown before and after the line an error is on
+const SNIPPET_CONTEXT_LINES: usize = 2;
+
+/// Represents a replacement suggested by the compiler
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Path of the file to change, relative to the web server project
+    pub file: String,
+    /// Byte offset where the replaced code starts
+    pub byte_start: usize,
+    /// Byte offset where the replaced code ends
+    pub byte_end: usize,
+    /// The code to put in its place
+    pub replacement: String,
+    /// Flag to determine if the compiler is sure the replacement is correct
+    pub is_machine_applicable: bool,
+}
+
+/// Represents an error reported by cargo build --message-format=json
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The error code (i.e. E0308), if there is one
+    pub code: Option<String>,
+    /// The error message
+    pub message: String,
+    /// Path of the file the error is in
+    pub file: String,
+    /// Line the error is on (starting at 1)
+    pub line: usize,
+    /// Column the error is on (starting at 1)
+    pub column: usize,
+    /// Replacements suggested by the compiler
+    pub suggestions: Vec<Suggestion>,
+}
+
+/// A compiler message as written by cargo, reduced to the fields that are used
+#[derive(Debug, Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    code: Option<CompilerCode>,
+    #[serde(default)]
+    spans: Vec<CompilerSpan>,
+    #[serde(default)]
+    children: Vec<CompilerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerCode {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompilerSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Collect the suggested replacements from the spans of a message and its children
+fn collect_suggestions(message: &CompilerMessage, suggestions: &mut Vec<Suggestion>) {
+    for span in &message.spans {
+        if let Some(replacement) = &span.suggested_replacement {
+            suggestions.push(Suggestion {
+                file: span.file_name.clone(),
+                byte_start: span.byte_start,
+                byte_end: span.byte_end,
+                replacement: replacement.clone(),
+                is_machine_applicable: span.suggestion_applicability.as_deref()
+                    == Some("MachineApplicable"),
+            });
+        }
+    }
+    for child in &message.children {
+        collect_suggestions(child, suggestions);
+    }
+}
+
+/// Parse the errors from the output of cargo build --message-format=json
+/// Lines that are not compiler messages (i.e. build script output) are skipped
+pub fn parse_cargo_diagnostics(stdout: &str) -> Vec<Diagnostic> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CargoMessage>(line).ok())
+        .filter(|cargo_message| cargo_message.reason == "compiler-message")
+        .filter_map(|cargo_message| cargo_message.message)
+        .filter(|message| message.level == "error")
+        .map(|message| {
+            let primary_span: Option<&CompilerSpan> = message
+                .spans
+                .iter()
+                .find(|span| span.is_primary)
+                .or(message.spans.first());
+            let mut suggestions: Vec<Suggestion> = vec![];
+            collect_suggestions(&message, &mut suggestions);
+
+            Diagnostic {
+                code: message.code.as_ref().map(|code| code.code.clone()),
+                message: message.message.clone(),
+                file: primary_span.map_or(String::new(), |span| span.file_name.clone()),
+                line: primary_span.map_or(0, |span| span.line_start),
+                column: primary_span.map_or(0, |span| span.column_start),
+                suggestions,
+            }
+        })
+        .collect()
+}
+
+/// Apply every machine-applicable suggestion to the files, returning only the files that changed
+/// Suggestions that overlap an earlier suggestion in the same file are skipped
+pub fn apply_machine_fixes(files: &[ProjectFile], diagnostics: &[Diagnostic]) -> Vec<ProjectFile> {
+    let mut changed: Vec<ProjectFile> = vec![];
+
+    for file in files {
+        let mut suggestions: Vec<&Suggestion> = diagnostics
+            .iter()
+            .flat_map(|diagnostic| diagnostic.suggestions.iter())
+            .filter(|suggestion| suggestion.is_machine_applicable && suggestion.file == file.path)
+            .collect();
+        if suggestions.is_empty() {
+            continue;
+        }
+
+        // Apply from the end of the file so earlier byte offsets stay valid
+        suggestions.sort_by_key(|suggestion| std::cmp::Reverse(suggestion.byte_start));
+        suggestions.dedup();
+
+        let mut content: String = file.content.clone();
+        let mut applied_from: usize = content.len();
+        for suggestion in suggestions {
+            let is_valid_range: bool = suggestion.byte_end <= applied_from
+                && suggestion.byte_start <= suggestion.byte_end
+                && content.is_char_boundary(suggestion.byte_start)
+                && content.is_char_boundary(suggestion.byte_end);
+            if !is_valid_range {
+                continue;
+            }
+            content.replace_range(
+                suggestion.byte_start..suggestion.byte_end,
+                &suggestion.replacement,
+            );
+            applied_from = suggestion.byte_start;
+        }
+
+        if content != file.content {
+            changed.push(ProjectFile {
+                path: file.path.clone(),
+                content,
+            });
+        }
+    }
+
+    changed
+}
+
+/// Show the lines around the line an error is on
+fn code_snippet(files: &[ProjectFile], path: &str, line: usize) -> String {
+    let file: &ProjectFile = match files.iter().find(|file| file.path == path) {
+        Some(file) if line > 0 => file,
+        _ => return String::new(),
+    };
+
+    let first_line: usize = line.saturating_sub(SNIPPET_CONTEXT_LINES).max(1);
+    file.content
+        .lines()
+        .enumerate()
+        .skip(first_line - 1)
+        .take(line - first_line + SNIPPET_CONTEXT_LINES + 1)
+        .map(|(index, code)| format!("{:>5} | {}", index + 1, code))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Describe the errors compactly for the LLM, with each distinct error listed once with its code
+pub fn compact_errors(diagnostics: &[Diagnostic], files: &[ProjectFile]) -> String {
+    let mut seen: Vec<(&Option<String>, &str, &str, usize)> = vec![];
+    let mut errors: Vec<String> = vec![];
+
+    for diagnostic in diagnostics {
+        let key = (
+            &diagnostic.code,
+            diagnostic.message.as_str(),
+            diagnostic.file.as_str(),
+            diagnostic.line,
+        );
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+
+        let code: String = diagnostic
+            .code
+            .as_ref()
+            .map_or(String::new(), |code| format!("[{}]", code));
+        let mut error: String = format!(
+            "error{}: {}\n --> {}:{}:{}",
+            code, diagnostic.message, diagnostic.file, diagnostic.line, diagnostic.column
+        );
+        let snippet: String = code_snippet(files, &diagnostic.file, diagnostic.line);
+        if !snippet.is_empty() {
+            error.push('\n');
+            error.push_str(&snippet);
+        }
+        errors.push(error);
+    }
+
+    errors.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_OUTPUT: &str = r#"{"reason":"compiler-artifact","package_id":"serde","target":{"name":"serde"}}
+{"reason":"compiler-message","message":{"message":"unused variable: `x`","level":"warning","code":{"code":"unused_variables"},"spans":[],"children":[]}}
+{"reason":"compiler-message","message":{"message":"cannot find type `Strng` in this scope","level":"error","code":{"code":"E0412"},"spans":[{"file_name":"src/models.rs","byte_start":32,"byte_end":37,"line_start":2,"column_start":15,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null}],"children":[{"message":"a struct with a similar name exists","level":"help","spans":[{"file_name":"src/models.rs","byte_start":32,"byte_end":37,"line_start":2,"column_start":15,"is_primary":true,"suggested_replacement":"String","suggestion_applicability":"MachineApplicable"}],"children":[]}]}}
+{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","byte_start":30,"byte_end":33,"line_start":3,"column_start":18,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null}],"children":[]}}
+{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","byte_start":30,"byte_end":33,"line_start":3,"column_start":18,"is_primary":true,"suggested_replacement":null,"suggestion_applicability":null}],"children":[]}}
+{"reason":"build-finished","success":false}"#;
+
+    fn test_files() -> Vec<ProjectFile> {
+        vec![
+            ProjectFile {
+                path: "src/main.rs".to_string(),
+                content: "mod models;\nfn main() {\n    let id: u64 = \"1\";\n}\n".to_string(),
+            },
+            ProjectFile {
+                path: "src/models.rs".to_string(),
+                content: "pub struct Item {\n    pub name: Strng,\n}\n".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn tests_parse_cargo_diagnostics() {
+        let diagnostics: Vec<Diagnostic> = parse_cargo_diagnostics(CARGO_OUTPUT);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E0412"));
+        assert_eq!(diagnostics[0].file, "src/models.rs");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 15));
+        assert_eq!(diagnostics[0].suggestions.len(), 1);
+        assert!(diagnostics[0].suggestions[0].is_machine_applicable);
+    }
+
+    #[test]
+    fn tests_apply_machine_fixes_and_compact_errors() {
+        let files: Vec<ProjectFile> = test_files();
+        let diagnostics: Vec<Diagnostic> = parse_cargo_diagnostics(CARGO_OUTPUT);
+
+        let changed: Vec<ProjectFile> = apply_machine_fixes(&files, &diagnostics);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            changed[0].content,
+            "pub struct Item {\n    pub name: String,\n}\n"
+        );
+
+        // The duplicate mismatched types error is only listed once
+        let remaining: Vec<Diagnostic> = diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.suggestions.is_empty())
+            .collect();
+        assert_eq!(
+            compact_errors(&remaining, &files),
+            "error[E0308]: mismatched types\n --> src/main.rs:3:18\n    1 | mod models;\n    2 | fn main() {\n    3 |     let id: u64 = \"1\";\n    4 | }"
+        );
+    }
+}
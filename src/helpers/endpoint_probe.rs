@@ -0,0 +1,474 @@
+use crate::models::agents::agent_traits::RouteObject;
+
+use reqwest::{Client, Method};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The part of a resource's lifecycle an endpoint covers, in the order the endpoints are called
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LifecyclePhase {
+    Create,
+    List,
+    Read,
+    Update,
+    Delete,
+}
+
+/// Represents a single request made while probing the endpoints
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeStep {
+    /// The endpoint to call
+    pub endpoint: RouteObject,
+    /// The method to call the endpoint with
+    pub method: Method,
+    /// The part of the lifecycle the call covers
+    pub phase: LifecyclePhase,
+    /// The resource the endpoint belongs to (i.e. "/item" for "/item/{id}")
+    pub resource: String,
+}
+
+/// Represents the ways a response did not match the response shape in the schema
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ContractMismatch {
+    /// The method of the request (i.e. "GET")
+    pub method: String,
+    /// The route that was called, with its dynamic segments filled
+    pub route: String,
+    /// A description of every mismatch (i.e. "$.name expected string, got number")
+    pub mismatches: Vec<String>,
+}
+
+/// Flag to determine if a route has segments that need filling (i.e. "/item/{id}")
+pub fn is_dynamic(endpoint: &RouteObject) -> bool {
+    endpoint.route.contains('{') || endpoint.is_route_dynamic.trim() == "true"
+}
+
+/// The resource a route belongs to, which is the part before its first dynamic segment
+fn resource_of(route: &str) -> String {
+    let resource: &str = route.split('{').next().unwrap_or(route);
+    match resource.trim_end_matches('/') {
+        "" => "/".to_string(),
+        resource => resource.to_string(),
+    }
+}
+
+/// The method to probe an endpoint with, or None if the method in the schema is not one the probe calls
+fn probe_method(endpoint: &RouteObject) -> Option<Method> {
+    match endpoint.method.trim().to_lowercase().as_str() {
+        "get" => Some(Method::GET),
+        "post" => Some(Method::POST),
+        "put" => Some(Method::PUT),
+        "patch" => Some(Method::PATCH),
+        "delete" => Some(Method::DELETE),
+        _ => None,
+    }
+}
+
+/// Find which part of the lifecycle an endpoint covers
+fn lifecycle_phase(method: &Method, endpoint: &RouteObject) -> LifecyclePhase {
+    match (method.as_str(), is_dynamic(endpoint)) {
+        ("POST", false) => LifecyclePhase::Create,
+        ("GET", false) => LifecyclePhase::List,
+        ("GET", true) => LifecyclePhase::Read,
+        ("DELETE", _) => LifecyclePhase::Delete,
+        _ => LifecyclePhase::Update,
+    }
+}
+
+/// Endpoints that are left out of the lifecycle because the probe cannot call their method (i.e. "options")
+pub fn unsupported_endpoints(endpoints: &[RouteObject]) -> Vec<&RouteObject> {
+    endpoints
+        .iter()
+        .filter(|endpoint| probe_method(endpoint).is_none())
+        .collect()
+}
+
+/// Order the endpoints so every resource is created, read, updated and deleted in turn
+/// Resources are probed in the order they first appear in the schema
+pub fn plan_lifecycle(endpoints: &[RouteObject]) -> Vec<ProbeStep> {
+    let mut resources: Vec<String> = vec![];
+    let mut steps: Vec<ProbeStep> = vec![];
+
+    for endpoint in endpoints {
+        let Some(method) = probe_method(endpoint) else {
+            continue;
+        };
+        let phase: LifecyclePhase = lifecycle_phase(&method, endpoint);
+        let resource: String = resource_of(&endpoint.route);
+        if !resources.contains(&resource) {
+            resources.push(resource.clone());
+        }
+        steps.push(ProbeStep {
+            endpoint: endpoint.clone(),
+            method,
+            phase,
+            resource,
+        });
+    }
+
+    // Stable, so endpoints in the same phase keep their schema order
+    steps.sort_by_key(|step| {
+        let resource_index: Option<usize> = resources.iter().position(|r| *r == step.resource);
+        (resource_index, step.phase)
+    });
+    steps
+}
+
+/// Make up a value matching a type hint from the schema (i.e. "number", "String", "Option<i64>")
+/// Updates use different values so a read after the update can tell them apart
+fn sample_value(hint: &Value, is_update: bool) -> Value {
+    match hint {
+        Value::String(hint) => match hint_kind(hint) {
+            Some("array") => Value::Array(vec![]),
+            Some("bool") => Value::Bool(!is_update),
+            Some("number") => serde_json::json!(if is_update { 2 } else { 1 }),
+            _ => Value::String(if is_update { "updated" } else { "sample" }.to_string()),
+        },
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, hint)| (key.clone(), sample_value(hint, is_update)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .first()
+                .map(|item| vec![sample_value(item, is_update)])
+                .unwrap_or_default(),
+        ),
+        // Example values given in the schema are used as they are
+        other => other.clone(),
+    }
+}
+
+/// Make up a request body from the request_body shape of an endpoint
+/// Returns None for endpoints that take no body (i.e. "None")
+/// # Arguments
+///
+/// * `request_body` - The request_body of the endpoint
+/// * `phase` - The part of the lifecycle the request covers
+/// * `sample_id` - The id to send in "id" fields, so created items can be found again
+pub fn sample_body(request_body: &Value, phase: LifecyclePhase, sample_id: u64) -> Option<Value> {
+    let fields: &Map<String, Value> = request_body.as_object()?;
+    let is_update: bool = phase == LifecyclePhase::Update;
+
+    Some(Value::Object(
+        fields
+            .iter()
+            .map(|(key, hint)| match key.as_str() {
+                "id" => (key.clone(), serde_json::json!(sample_id)),
+                _ => (key.clone(), sample_value(hint, is_update)),
+            })
+            .collect(),
+    ))
+}
+
+/// Read the id of a created item from a response (i.e. {"id": 3} or {"item": {"id": 3}})
+pub fn extract_id(body: &Value) -> Option<String> {
+    let id_to_string = |id: &Value| match id {
+        Value::Number(id) => Some(id.to_string()),
+        Value::String(id) if !id.is_empty() && !id.contains(char::is_whitespace) => {
+            Some(id.clone())
+        }
+        _ => None,
+    };
+
+    match body {
+        Value::Object(fields) => fields.get("id").and_then(id_to_string).or_else(|| {
+            fields
+                .values()
+                .filter(|value| value.is_object())
+                .find_map(|value| value.get("id").and_then(id_to_string))
+        }),
+        other => id_to_string(other),
+    }
+}
+
+/// Fill every dynamic segment of a route with an id (i.e. "/item/{id}" becomes "/item/3")
+pub fn fill_route(route: &str, id: &str) -> String {
+    let mut filled: String = String::new();
+    let mut rest: &str = route;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        filled.push_str(id);
+        rest = &rest[start + end + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// The JSON type a type hint from the schema stands for, or None if it cannot be told (i.e. "None" or a struct name)
+/// Optional hints stand for the type they wrap (i.e. "Option<i64>" is a number)
+fn hint_kind(hint: &str) -> Option<&'static str> {
+    let hint: String = hint.trim().to_lowercase();
+    let hint: &str = hint
+        .strip_prefix("option<")
+        .and_then(|inner| inner.strip_suffix('>'))
+        .unwrap_or(&hint);
+
+    if hint.starts_with("vec") || hint.contains("array") || hint.starts_with('[') {
+        Some("array")
+    } else if hint.contains("bool") {
+        Some("bool")
+    } else if [
+        "number", "int", "integer", "float", "u8", "u16", "u32", "u64", "usize", "i8", "i16",
+        "i32", "i64", "isize", "f32", "f64",
+    ]
+    .contains(&hint)
+    {
+        Some("number")
+    } else if ["string", "str", "&str"].contains(&hint) {
+        Some("string")
+    } else {
+        None
+    }
+}
+
+/// The JSON type of a value
+fn kind_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Flag to determine if a type hint allows the value to be missing or null (i.e. "Option<String>")
+fn is_optional(hint: &Value) -> bool {
+    hint.as_str()
+        .is_some_and(|hint| hint.trim().to_lowercase().starts_with("option<"))
+}
+
+/// Compare a value with its expected shape, adding a description of every mismatch
+fn check_shape(expected: &Value, actual: &Value, path: &str, mismatches: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(fields), Value::Object(actual_fields)) => {
+            for (key, hint) in fields {
+                let field_path: String = format!("{}.{}", path, key);
+                match actual_fields.get(key) {
+                    Some(value) => check_shape(hint, value, &field_path, mismatches),
+                    None if is_optional(hint) => {}
+                    None => mismatches.push(format!("{} is missing", field_path)),
+                }
+            }
+        }
+        // Only the first item is checked, as every item has the same shape
+        (Value::Array(items), Value::Array(actual_items)) => {
+            if let (Some(item), Some(actual_item)) = (items.first(), actual_items.first()) {
+                check_shape(item, actual_item, &format!("{}[0]", path), mismatches);
+            }
+        }
+        (Value::Object(_), _) | (Value::Array(_), _) => mismatches.push(format!(
+            "{} expected {}, got {}",
+            path,
+            kind_of(expected),
+            kind_of(actual)
+        )),
+        (Value::String(hint), _) => {
+            if actual.is_null() && is_optional(expected) {
+                return;
+            }
+            if let Some(kind) = hint_kind(hint) {
+                if kind != kind_of(actual) {
+                    mismatches.push(format!(
+                        "{} expected {}, got {}",
+                        path,
+                        kind,
+                        kind_of(actual)
+                    ));
+                }
+            }
+        }
+        // Example values and null shapes are not checked
+        _ => {}
+    }
+}
+
+/// Compare a response body with the response shape in the schema: field names, types and arrays vs objects
+/// Returns a description of every mismatch, with paths such as "$.items[0].name"
+pub fn validate_response(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut mismatches: Vec<String> = vec![];
+    check_shape(expected, actual, "$", &mut mismatches);
+    mismatches
+}
+
+/// Send a request to an endpoint, returning the status code and the JSON body (null if there is none)
+/// # Arguments
+///
+/// * `client` - The client making the request
+/// * `method` - The method of the request
+/// * `url` - The URL of the request
+/// * `body` - The JSON body to send, if any
+/// * `token` - The session token of the logged in user, for protected routes
+pub async fn send_probe(
+    client: &Client,
+    method: &Method,
+    url: &str,
+    body: Option<&Value>,
+    token: Option<&str>,
+) -> Result<(u16, Value), reqwest::Error> {
+    let mut request: reqwest::RequestBuilder = client.request(method.clone(), url);
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+
+    let response: reqwest::Response = request.send().await?;
+    let status_code: u16 = response.status().as_u16();
+    let text: String = response.text().await?;
+    Ok((
+        status_code,
+        serde_json::from_str(&text).unwrap_or(Value::Null),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_plan_lifecycle() {
+        let endpoints: Vec<RouteObject> = vec![
+            RouteObject::for_test("delete", "/item/{id}"),
+            RouteObject::for_test("get", "/item/{id}"),
+            RouteObject::for_test("get", "/price"),
+            RouteObject::for_test("put", "/item/{id}"),
+            RouteObject::for_test("post", "/item"),
+            RouteObject::for_test("get", "/item"),
+            RouteObject::for_test("options", "/item"),
+            RouteObject::for_test(" Patch ", "/item/{id}"),
+        ];
+
+        let steps: Vec<ProbeStep> = plan_lifecycle(&endpoints);
+        let planned: Vec<(LifecyclePhase, &str, &str)> = steps
+            .iter()
+            .map(|step| {
+                (
+                    step.phase,
+                    step.endpoint.method.as_str(),
+                    step.resource.as_str(),
+                )
+            })
+            .collect();
+        let expected: Vec<(LifecyclePhase, &str, &str)> = vec![
+            (LifecyclePhase::Create, "post", "/item"),
+            (LifecyclePhase::List, "get", "/item"),
+            (LifecyclePhase::Read, "get", "/item"),
+            (LifecyclePhase::Update, "put", "/item"),
+            (LifecyclePhase::Update, " Patch ", "/item"),
+            (LifecyclePhase::Delete, "delete", "/item"),
+            (LifecyclePhase::List, "get", "/price"),
+        ];
+        assert_eq!(planned, expected);
+        assert_eq!(steps[4].method, Method::PATCH);
+
+        // Methods the probe cannot call are reported instead of being sent as another method
+        let unsupported: Vec<&RouteObject> = unsupported_endpoints(&endpoints);
+        assert_eq!(unsupported, vec![&endpoints[6]]);
+    }
+
+    #[test]
+    fn tests_sample_body() {
+        let request_body: Value = serde_json::json!({
+            "id": "number",
+            "name": "string",
+            "completed": "bool",
+            "tags": "Vec<String>",
+            "priority": "i32",
+            "estimate": "Option<i64>",
+        });
+
+        let created: Option<Value> = sample_body(&request_body, LifecyclePhase::Create, 42);
+        assert_eq!(
+            created,
+            Some(serde_json::json!({
+                "id": 42, "name": "sample", "completed": true, "tags": [], "priority": 1, "estimate": 1
+            }))
+        );
+        assert_eq!(
+            sample_body(&request_body, LifecyclePhase::Update, 42),
+            Some(serde_json::json!({
+                "id": 42, "name": "updated", "completed": false, "tags": [], "priority": 2, "estimate": 2
+            }))
+        );
+
+        // Made up bodies match the shape they were made from
+        assert!(validate_response(&request_body, &created.unwrap()).is_empty());
+        // Without a body shape there is nothing to send, so the update is skipped
+        assert_eq!(
+            sample_body(
+                &Value::String("None".to_string()),
+                LifecyclePhase::Update,
+                42
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn tests_validate_response() {
+        let expected: Value = serde_json::json!({
+            "id": "number",
+            "name": "string",
+            "completed": "bool",
+            "note": "Option<String>",
+            "tags": [{ "label": "string" }],
+        });
+
+        let matching: Value = serde_json::json!({
+            "id": 1, "name": "a", "completed": false, "note": null, "tags": [{ "label": "x" }]
+        });
+        assert!(validate_response(&expected, &matching).is_empty());
+
+        let mismatching: Value = serde_json::json!({
+            "id": "1", "completed": false, "tags": [{ "label": 3 }]
+        });
+        assert_eq!(
+            validate_response(&expected, &mismatching),
+            vec![
+                "$.id expected number, got string".to_string(),
+                "$.name is missing".to_string(),
+                "$.tags[0].label expected string, got number".to_string(),
+            ]
+        );
+
+        assert_eq!(
+            validate_response(&serde_json::json!([expected]), &matching),
+            vec!["$ expected array, got object".to_string()]
+        );
+        assert!(validate_response(&serde_json::json!("None"), &Value::Null).is_empty());
+        assert!(
+            validate_response(&serde_json::json!("Vec<Item>"), &serde_json::json!([])).is_empty()
+        );
+    }
+
+    #[test]
+    fn tests_extract_id_and_fill_route() {
+        assert_eq!(
+            extract_id(&serde_json::json!({ "id": 3, "name": "a" })),
+            Some("3".to_string())
+        );
+        assert_eq!(
+            extract_id(&serde_json::json!({ "item": { "id": "abc" } })),
+            Some("abc".to_string())
+        );
+        assert_eq!(extract_id(&serde_json::json!(7)), Some("7".to_string()));
+        assert_eq!(extract_id(&serde_json::json!({ "name": "a" })), None);
+
+        assert_eq!(fill_route("/item/{id}", "3"), "/item/3");
+        assert_eq!(
+            fill_route("/user/{user_id}/item/{id}", "3"),
+            "/user/3/item/3"
+        );
+        assert_eq!(fill_route("/item", "3"), "/item");
+    }
+}
@@ -1,13 +1,38 @@
 use serde::de::DeserializeOwned;
-
-use reqwest::Client;
+use serde::Serialize;
 
 use crate::apis::call_request::call_gpt;
 use crate::helpers::command_line::PrintCommand;
+use crate::helpers::web_server::{web_server_project_path, INTEGRATION_TEST_TARGET};
+use crate::models::agents::agent_traits::{AgentError, FactSheet, ProjectFile};
 use crate::models::general::llm::Message;
 use std::env;
+use tokio_util::sync::CancellationToken;
 
 use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Marker placed before each file when the backend files are combined into one string
+const FILE_MARKER: &str = "// FILE: ";
+
+/// Suffix of the temporary files used while writing files atomically
+const TEMP_FILE_SUFFIX: &str = ".agent_tmp";
+
+/// Suffix of the copies kept of replaced files until every file has been written
+const BACKUP_FILE_SUFFIX: &str = ".agent_bak";
+
+#[cfg(test)]
+thread_local! {
+    /// Responses returned in order instead of calling the LLM, so agents can be tested offline
+    static QUEUED_LLM_RESPONSES: std::cell::RefCell<std::collections::VecDeque<String>> =
+        Default::default();
+}
+
+/// Return the response on the next LLM call made from this thread instead of calling the LLM
+#[cfg(test)]
+pub fn queue_llm_response(response: &str) {
+    QUEUED_LLM_RESPONSES.with(|responses| responses.borrow_mut().push_back(response.to_string()));
+}
 
 /// Extend AI function to encourage specific output
 /// This will help us get a specific output that we are expecting
@@ -36,71 +61,80 @@ pub fn extend_ai_function(ai_func: fn(&str) -> &'static str, func_input: &str) -
 }
 
 /// Performs call to LLM GPT
+/// Returns AgentError::Cancelled if the run is cancelled while waiting on the LLM
 /// # Arguments
 ///
 /// * `msg_context` - The message we are going to send as a function input
 /// * `agent_position` - The type of agent making the request
 /// * `agent_operation` - The operation the agent is actually doing
 /// * `function_pass` - The function that we are passing
+/// * `cancel` - Stops the call if the run is cancelled
 ///
 pub async fn ai_task_request(
     msg_context: String,
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> String {
+    cancel: &CancellationToken,
+) -> Result<String, AgentError> {
     // Extend AI function
     let extended_msg: Message = extend_ai_function(function_pass, &msg_context);
 
     // Print current status
     PrintCommand::AICall.print_agent_message(agent_position, agent_operation);
 
-    // Get LLM response
-    let llm_response_res: Result<String, Box<dyn std::error::Error + Send>> =
-        call_gpt(vec![extended_msg.clone()]).await;
+    #[cfg(test)]
+    if let Some(response) =
+        QUEUED_LLM_RESPONSES.with(|responses| responses.borrow_mut().pop_front())
+    {
+        return Ok(response);
+    }
 
-    // Return Success or try again
-    match llm_response_res {
-        Ok(llm_resp) => llm_resp,
-        Err(_) => call_gpt(vec![extended_msg.clone()])
-            .await
-            .expect("Failed twice to call OpenAI"),
+    // Get LLM response, trying again once unless the run was cancelled
+    for attempt in 1..=2 {
+        match call_gpt(vec![extended_msg.clone()], cancel).await {
+            Ok(llm_resp) => return Ok(llm_resp),
+            Err(_) if cancel.is_cancelled() => return Err(AgentError::Cancelled),
+            Err(e) if attempt == 2 => {
+                return Err(AgentError::Failed(format!(
+                    "Failed twice to call OpenAI: {}",
+                    e
+                )))
+            }
+            Err(_) => {}
+        }
     }
+    unreachable!("The last attempt always returns")
 }
 
 /// Performs call to LLM GPT - Decoded version
 /// We will get the string back from LLM and decode the string and create a struct
+/// Returns an error if the run is cancelled or the LLM response does not match the expected structure
 /// # Arguments
 ///
 /// * `msg_context` - The message we are going to send as a function input
 /// * `agent_position` - The type of agent making the request
 /// * `agent_operation` - The operation the agent is actually doing
 /// * `function_pass` - The function that we are passing
+/// * `cancel` - Stops the call if the run is cancelled
 ///
 pub async fn ai_task_request_decoded<T: DeserializeOwned>(
     msg_context: String,
     agent_position: &str,
     agent_operation: &str,
     function_pass: for<'a> fn(&'a str) -> &'static str,
-) -> T {
-    let llm_response: String =
-        ai_task_request(msg_context, agent_position, agent_operation, function_pass).await;
+    cancel: &CancellationToken,
+) -> Result<T, AgentError> {
+    let llm_response: String = ai_task_request(
+        msg_context,
+        agent_position,
+        agent_operation,
+        function_pass,
+        cancel,
+    )
+    .await?;
 
-    let decoded_response: T = serde_json::from_str(llm_response.as_str())
-        .expect("Failed to decode ai response from serde_json");
-
-    return decoded_response;
-}
-
-/// Check whether request URL is valid
-/// # Arguments
-///
-/// * `client` - The client making the request
-/// * `url` - The URL of the request
-///
-pub async fn check_status_code(client: &Client, url: &str) -> Result<u16, reqwest::Error> {
-    let response: reqwest::Response = client.get(url).send().await?;
-    Ok(response.status().as_u16())
+    Ok(serde_json::from_str(llm_response.as_str())?)
 }
 
 /// Read the code template from the web server project
@@ -113,22 +147,147 @@ pub fn read_code_template_contents() -> String {
     fs::read_to_string(path).expect("Failed to read code template")
 }
 
-/// Read the final version of the executable code
-pub fn read_exec_main_contents() -> String {
-    let exec_main_path: String =
-        env::var("EXEC_MAIN_PATH").expect("EXEC_MAIN_PATH not found in environment variables");
+/// Combine the backend files into one string so every file can be read (or scanned) at once
+/// Each file starts with a "// FILE: <path>" line
+pub fn render_project_files(files: &[ProjectFile]) -> String {
+    files
+        .iter()
+        .map(|file| format!("{}{}\n{}", FILE_MARKER, file.path, file.content))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
-    let path: String = String::from(exec_main_path);
-    fs::read_to_string(path).expect("Failed to read code template")
+/// Replace files that already exist with their changed version and add any new files
+pub fn merge_project_files(files: &mut Vec<ProjectFile>, changed: Vec<ProjectFile>) {
+    for changed_file in changed {
+        match files.iter_mut().find(|file| file.path == changed_file.path) {
+            Some(file) => file.content = changed_file.content,
+            None => files.push(changed_file),
+        }
+    }
+}
+
+/// Find the files named in an error message (i.e. "--> src/handlers.rs:12:5")
+/// Returns every file if none of them are named, so nothing is missed
+pub fn files_named_in(errors: &str, files: &[ProjectFile]) -> Vec<ProjectFile> {
+    let named: Vec<ProjectFile> = files
+        .iter()
+        .filter(|file| errors.contains(file.path.as_str()))
+        .cloned()
+        .collect();
+
+    if named.is_empty() {
+        files.to_vec()
+    } else {
+        named
+    }
+}
+
+/// Save changed backend files into the web server project and update the factsheet
+/// # Arguments
+///
+/// * `factsheet` - The factsheet holding the current backend files
+/// * `changed` - The files that were written or changed, with paths relative to the project
+pub fn save_backend_files(
+    factsheet: &mut FactSheet,
+    changed: Vec<ProjectFile>,
+) -> Result<(), String> {
+    write_files_atomically(&web_server_project_path(), &changed)?;
+
+    let mut files: Vec<ProjectFile> = factsheet.backend_files.take().unwrap_or_default();
+    merge_project_files(&mut files, changed);
+    factsheet.backend_code = Some(render_project_files(&files));
+    factsheet.backend_files = Some(files);
+    Ok(())
+}
+
+/// Read the backend files from disk again (i.e. after the user has edited them)
+pub fn reload_backend_files(factsheet: &mut FactSheet) -> Result<(), String> {
+    let project_path: PathBuf = web_server_project_path();
+    let mut files: Vec<ProjectFile> = factsheet.backend_files.take().unwrap_or_default();
+
+    for file in files.iter_mut() {
+        let path: PathBuf = resolve_project_path(&project_path, &file.path)?;
+        file.content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    }
+
+    factsheet.backend_code = Some(render_project_files(&files));
+    factsheet.backend_files = Some(files);
+    Ok(())
 }
 
-/// Save the backend code to file
-pub fn save_backend_code(contents: &String) {
-    let exec_main_path: String =
-        env::var("EXEC_MAIN_PATH").expect("EXEC_MAIN_PATH not found in environment variables");
+/// Write files inside a root directory so that either every file is written or none are
+/// Every file is first written next to its destination and only renamed into place once all writes succeed
+/// Replaced files are moved aside while renaming, so if any rename fails the files already renamed are put back
+/// # Arguments
+///
+/// * `root` - The directory the files must stay within
+/// * `files` - The files to write, with paths relative to the root
+pub fn write_files_atomically(root: &Path, files: &[ProjectFile]) -> Result<Vec<PathBuf>, String> {
+    // Reject every unsafe path before anything is written
+    let paths: Vec<PathBuf> = files
+        .iter()
+        .map(|file| resolve_project_path(root, &file.path))
+        .collect::<Result<Vec<PathBuf>, String>>()?;
+
+    let mut temp_paths: Vec<PathBuf> = vec![];
+    for (file, path) in files.iter().zip(paths.iter()) {
+        let mut temp_path: PathBuf = path.clone();
+        temp_path.as_mut_os_string().push(TEMP_FILE_SUFFIX);
+
+        let written: std::io::Result<()> = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temp_path, &file.content));
+        temp_paths.push(temp_path);
 
-    let path: String = String::from(exec_main_path);
-    fs::write(path, contents).expect("Failed to write main.rs file");
+        if let Err(e) = written {
+            temp_paths.iter().for_each(|temp_path| {
+                let _ = fs::remove_file(temp_path);
+            });
+            return Err(format!("Failed to write {}: {}", file.path, e));
+        }
+    }
+
+    // Every renamed file with the backup of the file it replaced, if there was one
+    let mut renamed: Vec<(&PathBuf, Option<PathBuf>)> = vec![];
+    for (index, (temp_path, path)) in temp_paths.iter().zip(paths.iter()).enumerate() {
+        let backup_path: Option<PathBuf> = path.exists().then(|| {
+            let mut backup_path: PathBuf = path.clone();
+            backup_path.as_mut_os_string().push(BACKUP_FILE_SUFFIX);
+            backup_path
+        });
+        let replaced: std::io::Result<()> = backup_path
+            .as_ref()
+            .map_or(Ok(()), |backup_path| fs::rename(path, backup_path))
+            .and_then(|_| fs::rename(temp_path, path));
+
+        if let Err(e) = replaced {
+            // Put back the file that was moved aside, then undo every earlier rename
+            if let Some(backup_path) = backup_path.filter(|backup_path| backup_path.exists()) {
+                let _ = fs::rename(&backup_path, path);
+            }
+            for (path, backup_path) in renamed.iter().rev() {
+                let _ = match backup_path {
+                    Some(backup_path) => fs::rename(backup_path, path),
+                    None => fs::remove_file(path),
+                };
+            }
+            temp_paths[index..].iter().for_each(|temp_path| {
+                let _ = fs::remove_file(temp_path);
+            });
+            return Err(format!("Failed to replace {}: {}", files[index].path, e));
+        }
+        renamed.push((path, backup_path));
+    }
+
+    for backup_path in renamed
+        .into_iter()
+        .filter_map(|(_, backup_path)| backup_path)
+    {
+        let _ = fs::remove_file(backup_path);
+    }
+    Ok(paths)
 }
 
 /// Save JSON API Endpoint Schema to a file
@@ -140,11 +299,218 @@ pub fn save_api_endpoints(api_endpoints: &String) {
     fs::write(path, api_endpoints).expect("Failed to write API Endpoints to file");
 }
 
+/// Save the integration tests into the tests directory of the web server project
+pub fn save_integration_tests(contents: &str) -> std::io::Result<PathBuf> {
+    let tests_path: PathBuf = web_server_project_path().join("tests");
+    fs::create_dir_all(&tests_path)?;
+
+    let path: PathBuf = tests_path.join(format!("{}.rs", INTEGRATION_TEST_TARGET));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Save a file at a path relative to the web server project
+/// The path is checked so that it stays inside the project
+pub fn save_project_file(relative_path: &str, contents: &str) -> Result<PathBuf, String> {
+    let file: ProjectFile = ProjectFile {
+        path: relative_path.to_string(),
+        content: contents.to_string(),
+    };
+    let mut paths: Vec<PathBuf> = write_files_atomically(&web_server_project_path(), &[file])?;
+    Ok(paths.remove(0))
+}
+
+/// Save the frontend files into the static directory of the web server project
+/// Every file path is checked so that it stays inside the static directory
+pub fn save_frontend_files(files: &[ProjectFile]) -> Result<(), String> {
+    let static_path: PathBuf = web_server_project_path().join("static");
+    write_files_atomically(&static_path, files)?;
+    Ok(())
+}
+
+/// Resolve a relative path inside a root directory
+/// Returns an error for absolute paths or paths that would escape the root (i.e. "../main.rs")
+/// # Arguments
+///
+/// * `root` - The directory the path must stay within
+/// * `relative_path` - The path provided by an agent
+pub fn resolve_project_path(root: &Path, relative_path: &str) -> Result<PathBuf, String> {
+    let path: &Path = Path::new(relative_path);
+    let mut resolved: PathBuf = root.to_path_buf();
+    let mut has_file_name: bool = false;
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                has_file_name = true;
+            }
+            Component::CurDir => {}
+            _ => return Err(format!("Rejected unsafe file path '{}'", relative_path)),
+        }
+    }
+
+    if !has_file_name {
+        return Err(format!("Rejected empty file path '{}'", relative_path));
+    }
+    Ok(resolved)
+}
+
+/// Directory where artifacts for the current run (checkpoints, logs, reports) are written
+/// Defaults to `run_output` when RUN_OUTPUT_PATH is not set,
+/// or to a temporary directory in tests so they never overwrite the artifacts of a real run
+pub fn run_output_path() -> PathBuf {
+    let path: PathBuf = match env::var("RUN_OUTPUT_PATH") {
+        Ok(run_output_path) => PathBuf::from(run_output_path),
+        Err(_) if cfg!(test) => env::temp_dir().join("lazy_agents_test_output"),
+        Err(_) => PathBuf::from("run_output"),
+    };
+    fs::create_dir_all(&path).expect("Failed to create run output directory");
+    path
+}
+
+/// Save a serializable value as pretty JSON in the run output directory
+/// # Arguments
+///
+/// * `file_name` - The name of the file inside the run output directory
+/// * `value` - The value to save
+pub fn save_run_artifact<T: Serialize>(file_name: &str, value: &T) -> std::io::Result<PathBuf> {
+    let path: PathBuf = run_output_path().join(file_name);
+    let contents: String = serde_json::to_string_pretty(value)?;
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Save the current state of the factsheet so an interrupted run can be inspected or resumed
+pub fn save_checkpoint(factsheet: &FactSheet) -> std::io::Result<PathBuf> {
+    save_run_artifact("checkpoint.json", factsheet)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
 
+    #[test]
+    fn tests_resolve_project_path() {
+        let root: &Path = Path::new("/tmp/web_server");
+
+        assert_eq!(
+            resolve_project_path(root, "static/./index.html"),
+            Ok(PathBuf::from("/tmp/web_server/static/index.html"))
+        );
+        assert!(resolve_project_path(root, "../secrets.txt").is_err());
+        assert!(resolve_project_path(root, "static/../../secrets.txt").is_err());
+        assert!(resolve_project_path(root, "/etc/passwd").is_err());
+        assert!(resolve_project_path(root, "").is_err());
+    }
+
+    #[test]
+    fn tests_merge_and_target_project_files() {
+        let file = |path: &str, content: &str| ProjectFile {
+            path: path.to_string(),
+            content: content.to_string(),
+        };
+        let mut files: Vec<ProjectFile> = vec![
+            file("src/main.rs", "mod models;"),
+            file("src/models.rs", "old"),
+        ];
+
+        merge_project_files(
+            &mut files,
+            vec![
+                file("src/models.rs", "new"),
+                file("src/handlers.rs", "fn h() {}"),
+            ],
+        );
+        assert_eq!(
+            render_project_files(&files),
+            "// FILE: src/main.rs\nmod models;\n// FILE: src/models.rs\nnew\n// FILE: src/handlers.rs\nfn h() {}"
+        );
+
+        let errors: &str = "error[E0308]: mismatched types\n --> src/handlers.rs:12:5";
+        let targeted: Vec<ProjectFile> = files_named_in(errors, &files);
+        assert_eq!(targeted, vec![file("src/handlers.rs", "fn h() {}")]);
+        assert_eq!(files_named_in("linking failed", &files).len(), 3);
+    }
+
+    #[test]
+    fn tests_write_files_atomically() {
+        let root: PathBuf = env::temp_dir().join("lazy_agents_tests_write_files_atomically");
+        let _ = fs::remove_dir_all(&root);
+
+        let files: Vec<ProjectFile> = vec![
+            ProjectFile {
+                path: "src/main.rs".to_string(),
+                content: "fn main() {}".to_string(),
+            },
+            ProjectFile {
+                path: "../escape.rs".to_string(),
+                content: "".to_string(),
+            },
+        ];
+
+        // Nothing is written when any path is unsafe
+        assert!(write_files_atomically(&root, &files).is_err());
+        assert!(!root.join("src/main.rs").exists());
+
+        write_files_atomically(&root, &files[..1]).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert!(!root.join("src/main.rs.agent_tmp").exists());
+
+        // A failed rename puts back every file already replaced
+        // (the directory at "assets" cannot be moved aside onto a backup that is not empty)
+        fs::create_dir_all(root.join("assets/css")).unwrap();
+        fs::create_dir_all(root.join("assets.agent_bak/css")).unwrap();
+        let replacing: Vec<ProjectFile> = vec![
+            ProjectFile {
+                path: "src/main.rs".to_string(),
+                content: "fn main() { println!(\"replaced\"); }".to_string(),
+            },
+            ProjectFile {
+                path: "src/lib.rs".to_string(),
+                content: "pub fn new_file() {}".to_string(),
+            },
+            ProjectFile {
+                path: "assets".to_string(),
+                content: "".to_string(),
+            },
+        ];
+        assert!(write_files_atomically(&root, &replacing).is_err());
+        assert_eq!(
+            fs::read_to_string(root.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        for leftover in [
+            "src/main.rs.agent_bak",
+            "src/lib.rs",
+            "src/lib.rs.agent_tmp",
+            "assets.agent_tmp",
+        ] {
+            assert!(
+                !root.join(leftover).exists(),
+                "{} was left behind",
+                leftover
+            );
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn tests_run_output_path() {
+        // Tests write their reports and logs to a temporary directory unless told otherwise
+        if env::var("RUN_OUTPUT_PATH").is_err() {
+            assert_eq!(
+                run_output_path(),
+                env::temp_dir().join("lazy_agents_test_output")
+            );
+        }
+    }
+
     #[test]
     fn tests_extending_ai_function() {
         let extended_msg: Message =
@@ -163,8 +529,10 @@ mod tests {
             "Managing Agent",
             "Defining user requirements",
             convert_user_input_to_goal,
+            &CancellationToken::new(),
         )
-        .await;
+        .await
+        .expect("Unable to call the LLM");
 
         assert!(res.len() > 20);
     }
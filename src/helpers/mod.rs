@@ -1,2 +1,10 @@
 pub mod command_line;
+pub mod diagnostics;
+pub mod endpoint_probe;
 pub mod general;
+pub mod patch;
+pub mod sandbox;
+pub mod scope_features;
+pub mod stub_server;
+pub mod url_profile;
+pub mod web_server;
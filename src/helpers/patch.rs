@@ -0,0 +1,197 @@
+use crate::models::agents::agent_traits::ProjectFile;
+use serde::{Deserialize, Serialize};
+
+/// Represents a search/replace edit to a single file
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CodeEdit {
+    /// Path of the file to edit, relative to the web server project
+    pub path: String,
+    /// The code to find, copied from the file
+    pub search: String,
+    /// The code to put in its place
+    pub replace: String,
+}
+
+/// Represents the edits made in one fix iteration, saved so the user can review what changed
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct PatchRecord {
+    /// The fix iteration of the run the edits were made in (starting at 1)
+    pub iteration: u8,
+    /// The edits suggested by the LLM
+    pub edits: Vec<CodeEdit>,
+    /// Why the edits could not be applied, if the files were regenerated instead
+    pub fallback_reason: Option<String>,
+}
+
+/// Find the only place the search lines appear in the content, ignoring indentation and trailing spaces
+/// Returns the range of matching lines
+fn find_fuzzy_match(content_lines: &[&str], search: &str) -> Option<(usize, usize)> {
+    let search_lines: Vec<&str> = search
+        .trim_matches('\n')
+        .lines()
+        .map(|line| line.trim())
+        .collect();
+    if search_lines.is_empty() || search_lines.len() > content_lines.len() {
+        return None;
+    }
+
+    let mut matches = (0..=content_lines.len() - search_lines.len()).filter(|&start| {
+        search_lines
+            .iter()
+            .enumerate()
+            .all(|(offset, line)| content_lines[start + offset].trim() == *line)
+    });
+
+    // An edit that matches more than one place is ambiguous, so it is not applied
+    match (matches.next(), matches.next()) {
+        (Some(start), None) => Some((start, start + search_lines.len())),
+        _ => None,
+    }
+}
+
+/// Apply a single edit to the content of a file
+/// Tries an exact match first, then a match that ignores indentation
+pub fn apply_edit(content: &str, search: &str, replace: &str) -> Option<String> {
+    if !search.trim().is_empty() && content.matches(search).count() == 1 {
+        return Some(content.replacen(search, replace, 1));
+    }
+
+    let content_lines: Vec<&str> = content.lines().collect();
+    let (start, end) = find_fuzzy_match(&content_lines, search)?;
+
+    let mut lines: Vec<&str> = content_lines[..start].to_vec();
+    lines.extend(replace.trim_matches('\n').lines());
+    lines.extend(&content_lines[end..]);
+
+    let mut patched: String = lines.join("\n");
+    if content.ends_with('\n') {
+        patched.push('\n');
+    }
+    Some(patched)
+}
+
+/// Apply every edit to the files, returning only the files that changed
+/// Fails without changing anything if any edit cannot be applied, or if the edits change nothing
+pub fn apply_edits(files: &[ProjectFile], edits: &[CodeEdit]) -> Result<Vec<ProjectFile>, String> {
+    if edits.is_empty() {
+        return Err("No edits were suggested".to_string());
+    }
+
+    let mut changed: Vec<ProjectFile> = vec![];
+
+    for edit in edits {
+        let file: &mut ProjectFile = match changed.iter_mut().find(|file| file.path == edit.path) {
+            Some(file) => file,
+            None => {
+                let file: &ProjectFile = files
+                    .iter()
+                    .find(|file| file.path == edit.path)
+                    .ok_or_else(|| format!("Edit targets unknown file {}", edit.path))?;
+                changed.push(file.clone());
+                changed.last_mut().unwrap()
+            }
+        };
+
+        file.content = apply_edit(&file.content, &edit.search, &edit.replace)
+            .ok_or_else(|| format!("Edit does not match exactly one place in {}", edit.path))?;
+    }
+
+    // Edits that replace code with the same code would use up a fix iteration without fixing anything
+    changed.retain(|file| !files.contains(file));
+    if changed.is_empty() {
+        return Err("The edits do not change any file".to_string());
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_apply_edit() {
+        let content: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", y);\n}\n";
+
+        // Exact match
+        assert_eq!(
+            apply_edit(content, "println!(\"{}\", y);", "println!(\"{}\", x);"),
+            Some("fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n".to_string())
+        );
+
+        // Indentation in the search does not have to match
+        assert_eq!(
+            apply_edit(
+                content,
+                "let x = 1;\nprintln!(\"{}\", y);",
+                "    let y = 1;\n    println!(\"{}\", y);"
+            ),
+            Some("fn main() {\n    let y = 1;\n    println!(\"{}\", y);\n}\n".to_string())
+        );
+
+        assert_eq!(apply_edit(content, "let z = 2;", "let z = 3;"), None);
+        assert_eq!(apply_edit("a\nb\na\n", "a", "c"), None);
+    }
+
+    #[test]
+    fn tests_apply_edits() {
+        let files: Vec<ProjectFile> = vec![
+            ProjectFile {
+                path: "src/main.rs".to_string(),
+                content: "mod models;\nfn main() {}\n".to_string(),
+            },
+            ProjectFile {
+                path: "src/models.rs".to_string(),
+                content: "pub struct Item { pub id: u32 }\n".to_string(),
+            },
+        ];
+        let edit = |path: &str, search: &str, replace: &str| CodeEdit {
+            path: path.to_string(),
+            search: search.to_string(),
+            replace: replace.to_string(),
+        };
+
+        let changed: Vec<ProjectFile> = apply_edits(
+            &files,
+            &[
+                edit("src/models.rs", "id: u32", "id: u64"),
+                edit(
+                    "src/models.rs",
+                    "pub struct Item",
+                    "#[derive(Debug)]\npub struct Item",
+                ),
+            ],
+        )
+        .unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            changed[0].content,
+            "#[derive(Debug)]\npub struct Item { pub id: u64 }\n"
+        );
+
+        assert!(apply_edits(&files, &[edit("src/db.rs", "a", "b")]).is_err());
+        assert!(apply_edits(&files, &[edit("src/main.rs", "fn other", "fn")]).is_err());
+    }
+
+    #[test]
+    fn tests_apply_edits_without_changes() {
+        let files: Vec<ProjectFile> = vec![ProjectFile {
+            path: "src/main.rs".to_string(),
+            content: "mod models;\nfn main() {}\n".to_string(),
+        }];
+        let no_op: CodeEdit = CodeEdit {
+            path: "src/main.rs".to_string(),
+            search: "fn main() {}".to_string(),
+            replace: "fn main() {}".to_string(),
+        };
+
+        // Both fall back to regenerating the files instead of counting as a fix
+        assert_eq!(
+            apply_edits(&files, &[]),
+            Err("No edits were suggested".to_string())
+        );
+        assert_eq!(
+            apply_edits(&files, &[no_op]),
+            Err("The edits do not change any file".to_string())
+        );
+    }
+}
@@ -0,0 +1,656 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{self, AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Tool that creates the namespaces and read-only mounts (bubblewrap)
+const SANDBOX_TOOL: &str = "bwrap";
+
+/// Bytes in a megabyte, as prlimit takes sizes in bytes
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// System directories holding the linker, libc and the tools the sandbox runs (timeout, prlimit)
+/// Mounted only if they exist, as the layout differs between distributions
+const SYSTEM_PATHS: [&str; 7] = [
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/lib",
+    "/lib64",
+    "/etc/alternatives",
+    "/etc/hosts",
+];
+
+/// Argument that makes this binary relay ports inside the sandbox instead of building a website
+const RELAY_FLAG: &str = "--sandbox-relay";
+
+/// How often a forwarded port is checked while waiting for the server behind it to start
+const FORWARD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Number of socket directories created so far, keeping the directories of concurrent sandboxes apart
+static SOCKET_DIRS_CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// The phases of work on the generated code, each of which can be sandboxed separately
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SandboxPhase {
+    /// Building the code and adding crates (cargo build, cargo add, cargo test --no-run)
+    Build,
+    /// Running the code (cargo run, cargo test)
+    Run,
+}
+
+/// Implementation of SandboxPhase
+impl SandboxPhase {
+    /// Name used in the environment variables of the phase (i.e. SANDBOX_BUILD_TIMEOUT_SECS)
+    fn env_name(&self) -> &'static str {
+        match self {
+            Self::Build => "BUILD",
+            Self::Run => "RUN",
+        }
+    }
+}
+
+/// Describes which phases run in the sandbox
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SandboxMode {
+    /// Nothing is sandboxed
+    Off,
+    /// Only building is sandboxed
+    Build,
+    /// Only running is sandboxed
+    Run,
+    /// Building and running are sandboxed
+    All,
+    /// Building and running are sandboxed, and the code is trusted to run without asking
+    Trusted,
+}
+
+/// Implementation of SandboxMode
+impl SandboxMode {
+    /// Read the mode from the SANDBOX environment variable ("off", "build", "run", "all" or "trusted")
+    /// The sandbox is off by default, as it needs bubblewrap installed
+    pub fn from_env() -> Self {
+        let mode: String = env::var("SANDBOX").unwrap_or_default();
+        match mode.trim().to_lowercase().as_str() {
+            "build" => Self::Build,
+            "run" => Self::Run,
+            "all" => Self::All,
+            "trusted" => Self::Trusted,
+            _ => Self::Off,
+        }
+    }
+
+    /// Flag to determine if a phase runs in the sandbox
+    pub fn is_sandboxed(&self, phase: SandboxPhase) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Build => phase == SandboxPhase::Build,
+            Self::Run => phase == SandboxPhase::Run,
+            Self::All | Self::Trusted => true,
+        }
+    }
+
+    /// Flag to determine if generated code may run without the user confirming it
+    /// Only the trusted sandbox allows this, and only while running has no network
+    pub fn is_run_confirmation_skipped(&self) -> bool {
+        *self == Self::Trusted && !is_network_allowed(SandboxPhase::Run)
+    }
+}
+
+/// Represents the limits placed on a sandboxed phase
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxConfig {
+    /// Flag to determine if the network is reachable (otherwise only loopback and forwarded ports are)
+    pub allow_network: bool,
+    /// Maximum CPU time in seconds
+    pub cpu_secs: u64,
+    /// Maximum address space in megabytes
+    pub memory_mb: u64,
+    /// Maximum size of a written file in megabytes
+    pub file_size_mb: u64,
+    /// Maximum number of processes
+    pub max_processes: u64,
+    /// Wall-clock time after which the process is killed
+    pub timeout: Duration,
+    /// Directories mounted read-only (the toolchain and the cargo registry)
+    pub read_only_paths: Vec<PathBuf>,
+    /// Directories mounted writable (the project, and the cargo home while building)
+    /// Nothing else of the host is mounted
+    pub writable_paths: Vec<PathBuf>,
+}
+
+/// Implementation of SandboxConfig
+impl SandboxConfig {
+    /// Default limits of a phase
+    /// Neither phase reaches the network: crates are downloaded before the sandbox starts,
+    /// and the server is reached through forwarded ports (see SandboxBridge)
+    /// Building writes to the cargo home, running only reads from it
+    pub fn defaults(phase: SandboxPhase, project_path: &Path) -> Self {
+        let mut read_only_paths: Vec<PathBuf> = SYSTEM_PATHS.iter().map(PathBuf::from).collect();
+        read_only_paths.extend(rustup_home());
+        let mut writable_paths: Vec<PathBuf> = vec![project_path.to_path_buf()];
+        match phase {
+            SandboxPhase::Build => {
+                writable_paths.extend(cargo_home());
+                Self {
+                    allow_network: false,
+                    cpu_secs: 900,
+                    memory_mb: 8192,
+                    file_size_mb: 2048,
+                    max_processes: 4096,
+                    timeout: Duration::from_secs(900),
+                    read_only_paths,
+                    writable_paths,
+                }
+            }
+            SandboxPhase::Run => {
+                read_only_paths.extend(cargo_home());
+                Self {
+                    allow_network: false,
+                    cpu_secs: 300,
+                    memory_mb: 2048,
+                    file_size_mb: 100,
+                    max_processes: 512,
+                    timeout: Duration::from_secs(600),
+                    read_only_paths,
+                    writable_paths,
+                }
+            }
+        }
+    }
+
+    /// Read the limits of a phase, overriding the defaults with SANDBOX_<PHASE>_<LIMIT> environment variables
+    /// (i.e. SANDBOX_RUN_MEMORY_MB=512 or SANDBOX_BUILD_NETWORK=false)
+    pub fn from_env(phase: SandboxPhase, project_path: &Path) -> Self {
+        let mut config: Self = Self::defaults(phase, project_path);
+        let read_u64 = |limit: &str, default: u64| {
+            read_phase_env(phase, limit)
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(default)
+        };
+
+        config.allow_network = is_network_allowed(phase);
+        config.cpu_secs = read_u64("CPU_SECS", config.cpu_secs);
+        config.memory_mb = read_u64("MEMORY_MB", config.memory_mb);
+        config.file_size_mb = read_u64("FILE_SIZE_MB", config.file_size_mb);
+        config.max_processes = read_u64("MAX_PROCESSES", config.max_processes);
+        config.timeout = Duration::from_secs(read_u64("TIMEOUT_SECS", config.timeout.as_secs()));
+        config
+    }
+}
+
+/// Read a SANDBOX_<PHASE>_<LIMIT> environment variable
+fn read_phase_env(phase: SandboxPhase, limit: &str) -> Option<String> {
+    env::var(format!("SANDBOX_{}_{}", phase.env_name(), limit)).ok()
+}
+
+/// Flag to determine if a sandboxed phase reaches the network, which needs SANDBOX_<PHASE>_NETWORK=true
+pub fn is_network_allowed(phase: SandboxPhase) -> bool {
+    read_phase_env(phase, "NETWORK")
+        .is_some_and(|network| network.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Directory cargo downloads crates into
+fn cargo_home() -> Option<PathBuf> {
+    env::var("CARGO_HOME").map(PathBuf::from).ok().or_else(|| {
+        env::var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join(".cargo"))
+    })
+}
+
+/// Directory rustup installs the toolchains into
+fn rustup_home() -> Option<PathBuf> {
+    env::var("RUSTUP_HOME").map(PathBuf::from).ok().or_else(|| {
+        env::var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join(".rustup"))
+    })
+}
+
+/// The only environment variables of the agent that cargo is given, so secrets such as the API key never reach generated code
+/// The cargo and rustup homes are set explicitly, as HOME is not passed on
+pub fn toolchain_env() -> Vec<(String, String)> {
+    let mut envs: Vec<(String, String)> = vec![];
+    if let Ok(path) = env::var("PATH") {
+        envs.push(("PATH".to_string(), path));
+    }
+    if let Some(cargo_home) = cargo_home() {
+        envs.push(("CARGO_HOME".to_string(), cargo_home.display().to_string()));
+    }
+    if let Some(rustup_home) = rustup_home() {
+        envs.push(("RUSTUP_HOME".to_string(), rustup_home.display().to_string()));
+    }
+    envs
+}
+
+/// Flag to determine if the sandbox tool is installed
+pub fn is_sandbox_available() -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|path| path.join(SANDBOX_TOOL).is_file()))
+}
+
+/// Represents a loopback port that is reachable across the boundary of a sandbox without network
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortForward {
+    /// A server inside the sandbox listens on the port, and is reached from the host (i.e. the web server)
+    Inbound(u16),
+    /// A server on the host listens on the port, and is reached from inside the sandbox (i.e. the stub server)
+    Outbound(u16),
+}
+
+/// Implementation of PortForward
+impl PortForward {
+    /// Argument describing the forward to the relay inside the sandbox (i.e. "in:8080")
+    fn to_arg(self) -> String {
+        match self {
+            Self::Inbound(port) => format!("in:{}", port),
+            Self::Outbound(port) => format!("out:{}", port),
+        }
+    }
+
+    /// Read a forward from its argument
+    fn from_arg(arg: &str) -> Option<Self> {
+        let (direction, port) = arg.split_once(':')?;
+        let port: u16 = port.parse().ok()?;
+        match direction {
+            "in" => Some(Self::Inbound(port)),
+            "out" => Some(Self::Outbound(port)),
+            _ => None,
+        }
+    }
+
+    /// Where connections are accepted and where they are sent to, on the host or inside the sandbox
+    /// Each port crosses the boundary through a Unix socket in the socket directory, which both sides mount
+    /// An inbound port is only listened on once the server behind it accepts connections,
+    /// so the host sees the port open exactly when the server is ready
+    fn endpoints(self, socket_dir: &Path, is_host: bool) -> (Endpoint, Endpoint, bool) {
+        match self {
+            Self::Inbound(port) => {
+                let socket: Endpoint = Endpoint::Unix(socket_dir.join(format!("in_{}.sock", port)));
+                if is_host {
+                    (Endpoint::Tcp(port), socket, true)
+                } else {
+                    (socket, Endpoint::Tcp(port), true)
+                }
+            }
+            Self::Outbound(port) => {
+                let socket: Endpoint =
+                    Endpoint::Unix(socket_dir.join(format!("out_{}.sock", port)));
+                if is_host {
+                    (socket, Endpoint::Tcp(port), false)
+                } else {
+                    (Endpoint::Tcp(port), socket, false)
+                }
+            }
+        }
+    }
+}
+
+/// Either side of a forwarded port
+#[derive(Debug, Clone, PartialEq)]
+enum Endpoint {
+    /// A port on loopback
+    Tcp(u16),
+    /// A Unix socket
+    Unix(PathBuf),
+}
+
+/// Implementation of Endpoint
+impl Endpoint {
+    /// Flag to determine if something accepts connections on the endpoint
+    async fn is_listening(&self) -> bool {
+        match self {
+            Self::Tcp(port) => TcpStream::connect(("127.0.0.1", *port)).await.is_ok(),
+            Self::Unix(path) => UnixStream::connect(path).await.is_ok(),
+        }
+    }
+}
+
+/// Accept connections on one endpoint and pipe each of them to the other endpoint
+async fn forward_port(
+    listen: Endpoint,
+    connect: Endpoint,
+    is_waiting: bool,
+) -> std::io::Result<()> {
+    if is_waiting {
+        while !connect.is_listening().await {
+            time::sleep(FORWARD_POLL_INTERVAL).await;
+        }
+    }
+
+    match listen {
+        Endpoint::Tcp(port) => {
+            let listener: TcpListener = TcpListener::bind(("127.0.0.1", port)).await?;
+            loop {
+                let (stream, _) = listener.accept().await?;
+                tokio::spawn(pipe_connection(stream, connect.clone()));
+            }
+        }
+        Endpoint::Unix(path) => {
+            let _ = fs::remove_file(&path);
+            let listener: UnixListener = UnixListener::bind(&path)?;
+            loop {
+                let (stream, _) = listener.accept().await?;
+                tokio::spawn(pipe_connection(stream, connect.clone()));
+            }
+        }
+    }
+}
+
+/// Copy one accepted connection to and from the endpoint it is forwarded to, until either side closes
+async fn pipe_connection<S: AsyncRead + AsyncWrite + Unpin>(mut accepted: S, connect: Endpoint) {
+    let _ = match connect {
+        Endpoint::Tcp(port) => match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(mut stream) => io::copy_bidirectional(&mut accepted, &mut stream).await,
+            Err(e) => Err(e),
+        },
+        Endpoint::Unix(path) => match UnixStream::connect(path).await {
+            Ok(mut stream) => io::copy_bidirectional(&mut accepted, &mut stream).await,
+            Err(e) => Err(e),
+        },
+    };
+}
+
+/// Represents the host side of the ports forwarded into a sandbox without network
+/// The sandbox side is relayed by this binary (see run_relay), started inside the sandbox in front of the command
+/// Forwarding stops and the sockets are removed when the bridge is dropped
+#[derive(Debug)]
+pub struct SandboxBridge {
+    /// Directory holding the Unix sockets, mounted into the sandbox
+    socket_dir: PathBuf,
+    /// The forwarded ports
+    forwards: Vec<PortForward>,
+    /// Tasks forwarding the ports on the host
+    forward_tasks: Vec<JoinHandle<std::io::Result<()>>>,
+}
+
+/// Implementation for the host side of forwarded ports
+impl SandboxBridge {
+    /// Start forwarding the ports on the host
+    pub fn start(forwards: &[PortForward]) -> std::io::Result<Self> {
+        let socket_dir: PathBuf = env::temp_dir().join(format!(
+            "sandbox_{}_{}",
+            process::id(),
+            SOCKET_DIRS_CREATED.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&socket_dir)?;
+
+        let forward_tasks: Vec<JoinHandle<std::io::Result<()>>> = forwards
+            .iter()
+            .map(|forward| {
+                let (listen, connect, is_waiting) = forward.endpoints(&socket_dir, true);
+                tokio::spawn(forward_port(listen, connect, is_waiting))
+            })
+            .collect();
+
+        Ok(Self {
+            socket_dir,
+            forwards: forwards.to_vec(),
+            forward_tasks,
+        })
+    }
+}
+
+/// Stop forwarding once the sandbox has exited
+impl Drop for SandboxBridge {
+    fn drop(&mut self) {
+        for forward_task in &self.forward_tasks {
+            forward_task.abort();
+        }
+        let _ = fs::remove_dir_all(&self.socket_dir);
+    }
+}
+
+/// Relay the forwarded ports inside the sandbox while running the command
+/// Returns None if this binary was not started as the relay
+/// # Arguments
+///
+/// * `args` - The arguments of this binary: --sandbox-relay <socket dir> <forwards> -- <program> <args>
+pub async fn run_relay(args: &[String]) -> Option<i32> {
+    let (flag, args) = args.split_first()?;
+    if flag != RELAY_FLAG {
+        return None;
+    }
+
+    let separator: usize = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let (relay_args, command) = args.split_at(separator);
+    let Some((socket_dir, forwards)) = relay_args.split_first() else {
+        eprintln!("The sandbox relay needs a socket directory");
+        return Some(1);
+    };
+    let Some((program, program_args)) = command.get(1..).and_then(|command| command.split_first())
+    else {
+        eprintln!("The sandbox relay needs a command to run");
+        return Some(1);
+    };
+
+    for forward in forwards {
+        let Some(forward) = PortForward::from_arg(forward) else {
+            eprintln!("Unknown port forward '{}'", forward);
+            return Some(1);
+        };
+        let (listen, connect, is_waiting) = forward.endpoints(Path::new(socket_dir), false);
+        tokio::spawn(forward_port(listen, connect, is_waiting));
+    }
+
+    match Command::new(program).args(program_args).status().await {
+        Ok(status) => Some(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Failed to run {}: {}", program, e);
+            Some(1)
+        }
+    }
+}
+
+/// Wrap a command so it runs in the sandbox
+/// Returns the program and arguments to run instead:
+/// bwrap (namespaces, a cleared environment and only the mounts of the config), then the relay of the bridge if any,
+/// then timeout (wall-clock limit), then prlimit (rlimits), then the command
+/// # Arguments
+///
+/// * `program` - The program to run (i.e. "cargo")
+/// * `args` - The arguments of the program
+/// * `envs` - The only environment variables set inside the sandbox (see toolchain_env)
+/// * `project_path` - The directory the program runs in
+/// * `config` - The limits of the sandbox
+/// * `bridge` - The ports forwarded into the sandbox, if it has no network
+pub fn sandboxed_command(
+    program: &str,
+    args: &[&str],
+    envs: &[(String, String)],
+    project_path: &Path,
+    config: &SandboxConfig,
+    bridge: Option<&SandboxBridge>,
+) -> (String, Vec<String>) {
+    let mut sandbox_args: Vec<String> = vec!["--clearenv".to_string()];
+    for (key, value) in envs {
+        sandbox_args.extend(["--setenv".to_string(), key.clone(), value.clone()]);
+    }
+    sandbox_args.extend(
+        ["--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]
+            .iter()
+            .map(|arg| arg.to_string()),
+    );
+
+    // Bound after /tmp so a path inside /tmp is not hidden by the empty tmpfs
+    for path in &config.read_only_paths {
+        let path: String = path.display().to_string();
+        sandbox_args.extend(["--ro-bind-try".to_string(), path.clone(), path]);
+    }
+    let mut writable_paths: Vec<&Path> =
+        config.writable_paths.iter().map(PathBuf::as_path).collect();
+    let relay_exe: Option<PathBuf> = bridge.and_then(|_| env::current_exe().ok());
+    if let Some(bridge) = bridge {
+        writable_paths.push(&bridge.socket_dir);
+    }
+    for path in writable_paths {
+        let path: String = path.display().to_string();
+        sandbox_args.extend(["--bind".to_string(), path.clone(), path]);
+    }
+    if let Some(relay_exe) = &relay_exe {
+        let relay_exe: String = relay_exe.display().to_string();
+        sandbox_args.extend(["--ro-bind".to_string(), relay_exe.clone(), relay_exe]);
+    }
+
+    // A new network namespace only has loopback, which bubblewrap brings up
+    if !config.allow_network {
+        sandbox_args.push("--unshare-net".to_string());
+    }
+    sandbox_args.extend([
+        "--unshare-pid".to_string(),
+        "--die-with-parent".to_string(),
+        "--chdir".to_string(),
+        project_path.display().to_string(),
+        "--".to_string(),
+    ]);
+
+    if let (Some(bridge), Some(relay_exe)) = (bridge, &relay_exe) {
+        sandbox_args.extend([
+            relay_exe.display().to_string(),
+            RELAY_FLAG.to_string(),
+            bridge.socket_dir.display().to_string(),
+        ]);
+        sandbox_args.extend(bridge.forwards.iter().map(|forward| forward.to_arg()));
+        sandbox_args.push("--".to_string());
+    }
+    sandbox_args.extend([
+        "timeout".to_string(),
+        "--signal=KILL".to_string(),
+        config.timeout.as_secs().to_string(),
+        "prlimit".to_string(),
+        format!("--cpu={}", config.cpu_secs),
+        format!("--as={}", config.memory_mb * MEGABYTE),
+        format!("--fsize={}", config.file_size_mb * MEGABYTE),
+        format!("--nproc={}", config.max_processes),
+        "--".to_string(),
+        program.to_string(),
+    ]);
+    sandbox_args.extend(args.iter().map(|arg| arg.to_string()));
+
+    (SANDBOX_TOOL.to_string(), sandbox_args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_sandbox_mode() {
+        assert!(SandboxMode::Build.is_sandboxed(SandboxPhase::Build));
+        assert!(!SandboxMode::Build.is_sandboxed(SandboxPhase::Run));
+        assert!(SandboxMode::Trusted.is_sandboxed(SandboxPhase::Run));
+        assert!(!SandboxMode::Off.is_sandboxed(SandboxPhase::Build));
+    }
+
+    #[tokio::test]
+    async fn tests_sandboxed_command() {
+        let project_path: &Path = Path::new("/tmp/web_server");
+        let config: SandboxConfig = SandboxConfig {
+            allow_network: false,
+            cpu_secs: 300,
+            memory_mb: 512,
+            file_size_mb: 100,
+            max_processes: 512,
+            timeout: Duration::from_secs(600),
+            read_only_paths: vec![PathBuf::from("/usr")],
+            writable_paths: vec![project_path.to_path_buf()],
+        };
+        let envs: Vec<(String, String)> = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("PORT".to_string(), "8080".to_string()),
+        ];
+
+        let (program, args) =
+            sandboxed_command("cargo", &["run"], &envs, project_path, &config, None);
+        assert_eq!(program, "bwrap");
+        assert_eq!(
+            args.join(" "),
+            "--clearenv --setenv PATH /usr/bin --setenv PORT 8080 \
+             --dev /dev --proc /proc --tmpfs /tmp --ro-bind-try /usr /usr --bind /tmp/web_server /tmp/web_server \
+             --unshare-net --unshare-pid --die-with-parent --chdir /tmp/web_server \
+             -- timeout --signal=KILL 600 prlimit --cpu=300 --as=536870912 --fsize=104857600 --nproc=512 \
+             -- cargo run"
+        );
+
+        // Forwarded ports are relayed inside the sandbox through the mounted socket directory
+        let bridge: SandboxBridge =
+            SandboxBridge::start(&[PortForward::Inbound(8080), PortForward::Outbound(9090)])
+                .unwrap();
+        let (_, bridged_args) = sandboxed_command(
+            "cargo",
+            &["run"],
+            &envs,
+            project_path,
+            &config,
+            Some(&bridge),
+        );
+        let socket_dir: String = bridge.socket_dir.display().to_string();
+        let bridged_args: String = bridged_args.join(" ");
+        assert!(bridged_args.contains(&format!("--bind {} {}", socket_dir, socket_dir)));
+        assert!(bridged_args.contains(&format!(
+            "--sandbox-relay {} in:8080 out:9090 -- timeout",
+            socket_dir
+        )));
+
+        // Running has no network by default, and only the project is writable
+        let run_config: SandboxConfig = SandboxConfig::defaults(SandboxPhase::Run, project_path);
+        assert!(!run_config.allow_network);
+        assert_eq!(run_config.writable_paths, vec![project_path.to_path_buf()]);
+    }
+
+    #[tokio::test]
+    async fn tests_forward_port() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A server on loopback answering "ping" with "pong"
+        let server: TcpListener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port: u16 = server.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = server.accept().await {
+                let mut request: [u8; 4] = [0; 4];
+                if stream.read_exact(&mut request).await.is_ok() {
+                    let _ = stream.write_all(b"pong").await;
+                }
+            }
+        });
+
+        // The host side of an outbound port and the sandbox side of an inbound port both lead to the server
+        let bridge: SandboxBridge = SandboxBridge::start(&[PortForward::Outbound(port)]).unwrap();
+        let (listen, connect, is_waiting) =
+            PortForward::Inbound(port).endpoints(&bridge.socket_dir, false);
+        tokio::spawn(forward_port(listen, connect, is_waiting));
+
+        for direction in ["out", "in"] {
+            let socket: PathBuf = bridge
+                .socket_dir
+                .join(format!("{}_{}.sock", direction, port));
+            let mut stream: UnixStream = loop {
+                if let Ok(stream) = UnixStream::connect(&socket).await {
+                    break stream;
+                }
+                time::sleep(FORWARD_POLL_INTERVAL).await;
+            };
+            stream.write_all(b"ping").await.unwrap();
+            let mut reply: [u8; 4] = [0; 4];
+            stream.read_exact(&mut reply).await.unwrap();
+            assert_eq!(&reply, b"pong");
+        }
+
+        let socket_dir: PathBuf = bridge.socket_dir.clone();
+        drop(bridge);
+        assert!(!socket_dir.exists());
+
+        assert_eq!(run_relay(&["build a blog".to_string()]).await, None);
+    }
+}
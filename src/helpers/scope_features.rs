@@ -0,0 +1,215 @@
+use crate::models::agents::agent_traits::{PersistenceType, ProjectScope, RealtimeType};
+
+/// Represents a requirement from the project scope that changes what is built and tested
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScopeFeature {
+    /// Short name of the feature (i.e. "pagination")
+    pub name: &'static str,
+    /// What the backend has to build for the feature
+    pub backend_instruction: &'static str,
+    /// Arguments for "cargo add" that add the crates the feature needs (i.e. "rusqlite@0.31 --features bundled")
+    pub crates: &'static [&'static str],
+    /// What the integration tests have to check for the feature
+    pub test_instruction: &'static str,
+    /// Code of which at least one piece has to appear in the backend for the feature to count as built
+    /// Empty if the feature is only recognised by what is missing from the code
+    pub code_markers: &'static [&'static str],
+    /// Code that must not appear in the backend for the feature to count as built
+    pub absent_markers: &'static [&'static str],
+}
+
+/// Implementation of ScopeFeature
+impl ScopeFeature {
+    /// Check the backend code for the traces the feature leaves, and for any code that contradicts it
+    pub fn is_built(&self, backend_code: &str) -> bool {
+        let has_marker: bool = self.code_markers.is_empty()
+            || self
+                .code_markers
+                .iter()
+                .any(|marker| backend_code.contains(marker));
+        let has_absent_marker: bool = self
+            .absent_markers
+            .iter()
+            .any(|marker| backend_code.contains(marker));
+        has_marker && !has_absent_marker
+    }
+}
+
+const IN_MEMORY: ScopeFeature = ScopeFeature {
+    name: "in_memory",
+    backend_instruction: "Keeps all data in memory in the app state (i.e. Mutex<Vec<..>>) and does not write a database file",
+    crates: &[],
+    test_instruction: "Does not expect data to survive a restart of the server",
+    code_markers: &[],
+    absent_markers: &["database.json", "fs::write", "File::create"],
+};
+
+const SQLITE: ScopeFeature = ScopeFeature {
+    name: "sqlite",
+    backend_instruction: "Stores all data in a SQLite database file (database.sqlite) with the rusqlite crate instead of the JSON file database, creating the tables on start up",
+    crates: &["rusqlite@0.31 --features bundled"],
+    test_instruction: "Checks that created items can be read back in a later request",
+    code_markers: &["rusqlite"],
+    absent_markers: &["database.json"],
+};
+
+const WEBSOCKETS: ScopeFeature = ScopeFeature {
+    name: "websockets",
+    backend_instruction: "Pushes every create, update and delete to connected clients as JSON over a websocket at GET /ws, using the actix-ws crate",
+    crates: &["actix-ws@0.2"],
+    test_instruction: "Checks that GET /ws without websocket headers does not respond with 404",
+    code_markers: &["actix_ws", "/ws"],
+    absent_markers: &[],
+};
+
+const SSE: ScopeFeature = ScopeFeature {
+    name: "sse",
+    backend_instruction: "Pushes every create, update and delete to connected clients as server-sent events from GET /events with the content type text/event-stream",
+    crates: &[],
+    test_instruction: "Checks that GET /events responds with the content type text/event-stream",
+    code_markers: &["text/event-stream"],
+    absent_markers: &[],
+};
+
+const PAGINATION: ScopeFeature = ScopeFeature {
+    name: "pagination",
+    backend_instruction: "Every route listing items takes optional ?page= (starting at 1) and ?per_page= (default 20) query parameters and returns only that page",
+    crates: &[],
+    test_instruction: "Creates 3 items and checks that ?page=1&per_page=2 returns 2 of them and ?page=2&per_page=2 the rest",
+    code_markers: &["per_page"],
+    absent_markers: &[],
+};
+
+const FILE_UPLOADS: ScopeFeature = ScopeFeature {
+    name: "uploads",
+    backend_instruction: "Accepts file uploads as multipart/form-data at POST /upload with the actix-multipart crate, saving them in an uploads directory and returning {\"filename\": ...}",
+    crates: &["actix-multipart@0.6", "futures-util@0.3"],
+    test_instruction: "Uploads a small text file with a multipart request and checks the returned filename",
+    code_markers: &["actix_multipart", "Multipart"],
+    absent_markers: &[],
+};
+
+const SCHEDULED_JOBS: ScopeFeature = ScopeFeature {
+    name: "jobs",
+    backend_instruction: "Runs scheduled work in the background with tokio::spawn and tokio::time::interval, started from main before the server",
+    crates: &[],
+    test_instruction: "Checks that the server keeps responding while the scheduled work runs",
+    code_markers: &["interval("],
+    absent_markers: &[],
+};
+
+const RATE_LIMITING: ScopeFeature = ScopeFeature {
+    name: "ratelimit",
+    backend_instruction: "Limits each client IP to 100 requests a minute, responding 429 Too Many Requests once the limit is reached, with the counts kept in the app state",
+    crates: &[],
+    test_instruction: "Does not send more than 100 requests a minute, so the rate limit is never reached by the other tests",
+    code_markers: &["TooManyRequests()", "StatusCode::TOO_MANY_REQUESTS"],
+    absent_markers: &[],
+};
+
+const ADMIN_ROLES: ScopeFeature = ScopeFeature {
+    name: "admin",
+    backend_instruction: "Users have a role of \"user\" or \"admin\" (the first user to register is the admin), and routes changing other users' data respond 403 Forbidden to non admins",
+    crates: &[],
+    test_instruction: "Checks that a second registered user gets 403 from admin only routes",
+    code_markers: &[" role:", ".role", "Role::"],
+    absent_markers: &[],
+};
+
+const I18N: ScopeFeature = ScopeFeature {
+    name: "i18n",
+    backend_instruction: "Translates every user facing message into the language in the Accept-Language header (at least en and es), falling back to en",
+    crates: &[],
+    test_instruction: "Checks that an error message differs between Accept-Language: en and Accept-Language: es",
+    code_markers: &["Accept-Language", "accept-language", "ACCEPT_LANGUAGE"],
+    absent_markers: &[],
+};
+
+/// Features the project scope turns on, in the order they should be built
+/// Storing data in a JSON file is what the code template does, so it does not need a feature
+pub fn active_features(project_scope: &ProjectScope) -> Vec<ScopeFeature> {
+    let mut features: Vec<ScopeFeature> = vec![];
+
+    match project_scope.persistence {
+        PersistenceType::None => features.push(IN_MEMORY),
+        PersistenceType::JsonFile => {}
+        PersistenceType::Sqlite => features.push(SQLITE),
+    }
+    match project_scope.realtime {
+        RealtimeType::None => {}
+        RealtimeType::Websockets => features.push(WEBSOCKETS),
+        RealtimeType::Sse => features.push(SSE),
+    }
+
+    let flagged_features: [(bool, ScopeFeature); 6] = [
+        (project_scope.is_pagination_required, PAGINATION),
+        (project_scope.is_file_upload_required, FILE_UPLOADS),
+        (project_scope.is_scheduled_jobs_required, SCHEDULED_JOBS),
+        (project_scope.is_rate_limiting_required, RATE_LIMITING),
+        (project_scope.is_admin_roles_required, ADMIN_ROLES),
+        (project_scope.is_i18n_required, I18N),
+    ];
+    features.extend(
+        flagged_features
+            .into_iter()
+            .filter(|(is_required, _)| *is_required)
+            .map(|(_, feature)| feature),
+    );
+    features
+}
+
+/// Describe the features for an LLM prompt, one instruction per feature
+/// Empty if no feature is active
+pub fn describe_features(
+    features: &[ScopeFeature],
+    instruction: fn(&ScopeFeature) -> &str,
+) -> String {
+    features
+        .iter()
+        .map(|feature| format!("{}: {}", feature.name, instruction(feature)))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_active_features() {
+        let project_scope: ProjectScope = ProjectScope {
+            is_crud_required: true,
+            persistence: PersistenceType::Sqlite,
+            realtime: RealtimeType::Sse,
+            is_pagination_required: true,
+            is_i18n_required: true,
+            ..Default::default()
+        };
+
+        let names: Vec<&str> = active_features(&project_scope)
+            .iter()
+            .map(|feature| feature.name)
+            .collect();
+        assert_eq!(names, vec!["sqlite", "sse", "pagination", "i18n"]);
+
+        // The template already stores data in a JSON file
+        assert!(active_features(&ProjectScope::default()).is_empty());
+
+        // Keeping data in memory is contradicted by writing the database file
+        assert!(IN_MEMORY.is_built("let items: Mutex<Vec<Item>> = Mutex::new(vec![]);"));
+        assert!(!IN_MEMORY.is_built("fs::write(\"database.json\", contents)"));
+
+        // Mentioning admins is not enough, users need a role that is checked
+        assert!(!ADMIN_ROLES.is_built("// TODO: admin only"));
+        assert!(ADMIN_ROLES
+            .is_built("if user.role != \"admin\" { return HttpResponse::Forbidden().finish(); }"));
+
+        // A 429 has to be sent as a response, not just appear in the code
+        assert!(!RATE_LIMITING.is_built("const LIMIT_STATUS: u16 = 429;"));
+        assert!(RATE_LIMITING.is_built("return HttpResponse::TooManyRequests().finish();"));
+
+        let described: String =
+            describe_features(&[PAGINATION], |feature| feature.test_instruction);
+        assert!(described.starts_with("pagination: Creates 3 items"));
+    }
+}
@@ -0,0 +1,349 @@
+use crate::helpers::general::{run_output_path, save_run_artifact};
+
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Name of the file in the run output directory that the external URL responses are saved to
+const URL_SNAPSHOTS_FILE: &str = "url_snapshots.json";
+
+/// Largest request head the stub server reads, as it only needs the request line
+const MAX_REQUEST_HEAD_BYTES: usize = 16 * 1024;
+
+/// Represents a response recorded from an external URL, which the stub server replays
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UrlSnapshot {
+    /// The URL that was called
+    pub url: String,
+    /// The status code it responded with
+    pub status: u16,
+    /// The content type it responded with, if any
+    pub content_type: Option<String>,
+    /// The body it responded with
+    pub body: String,
+}
+
+/// Record the response of an external URL so it can be replayed
+/// # Arguments
+///
+/// * `url` - The URL that was called, which the stub server serves the response for
+/// * `response` - The response it gave
+///
+pub async fn read_url_snapshot(
+    url: &str,
+    response: reqwest::Response,
+) -> Result<UrlSnapshot, reqwest::Error> {
+    let status: u16 = response.status().as_u16();
+    let content_type: Option<String> = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body: String = response.text().await?;
+
+    Ok(UrlSnapshot {
+        url: url.to_string(),
+        status,
+        content_type,
+        body,
+    })
+}
+
+/// Save the recorded responses in the run output directory
+pub fn save_url_snapshots(snapshots: &[UrlSnapshot]) -> std::io::Result<PathBuf> {
+    save_run_artifact(URL_SNAPSHOTS_FILE, &snapshots)
+}
+
+/// Load the recorded responses of the given URLs from the run output directory
+/// Responses of URLs that are no longer used are left out, and a missing file means nothing was recorded
+pub fn load_url_snapshots(urls: &[String]) -> Vec<UrlSnapshot> {
+    let contents: String =
+        fs::read_to_string(run_output_path().join(URL_SNAPSHOTS_FILE)).unwrap_or_default();
+    let snapshots: Vec<UrlSnapshot> = serde_json::from_str(&contents).unwrap_or_default();
+
+    snapshots
+        .into_iter()
+        .filter(|snapshot| urls.contains(&snapshot.url))
+        .collect()
+}
+
+/// Name of the environment variable the web server reads the base URL of an external host from
+/// (i.e. https://api.example.com/v1/rates is read from EXTERNAL_API_EXAMPLE_COM_BASE_URL)
+pub fn external_base_url_env_var(url: &str) -> Option<String> {
+    let host: String = Url::parse(url).ok()?.host_str()?.to_string();
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Some(format!("EXTERNAL_{}_BASE_URL", name))
+}
+
+/// Describe the environment variable and real base URL of every external host, for the LLM
+/// (i.e. "EXTERNAL_API_EXAMPLE_COM_BASE_URL" => "https://api.example.com")
+pub fn external_base_urls(urls: &[String]) -> Vec<(String, String)> {
+    let mut base_urls: Vec<(String, String)> = vec![];
+    for url in urls {
+        let (Some(env_var), Ok(parsed)) = (external_base_url_env_var(url), Url::parse(url)) else {
+            continue;
+        };
+        let base_url: String = parsed.origin().ascii_serialization();
+        if !base_urls.iter().any(|(existing, _)| existing == &env_var) {
+            base_urls.push((env_var, base_url));
+        }
+    }
+    base_urls
+}
+
+/// Represents a local server that replays the recorded responses of external URLs
+/// Each host is served under its own path prefix (i.e. http://127.0.0.1:<port>/api.example.com/v1/rates)
+/// The server stops when it is dropped
+#[derive(Debug)]
+pub struct StubServer {
+    /// The port the stub server listens on
+    port: u16,
+    /// Task accepting connections
+    listener_task: JoinHandle<()>,
+}
+
+/// Implementation for the local server replaying external URLs
+impl StubServer {
+    /// Start the stub server on a free port on loopback
+    pub async fn start(snapshots: Vec<UrlSnapshot>) -> std::io::Result<Self> {
+        let listener: TcpListener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let port: u16 = listener.local_addr()?.port();
+
+        let listener_task: JoinHandle<()> = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let snapshots: Vec<UrlSnapshot> = snapshots.clone();
+                tokio::spawn(async move {
+                    let _ = serve_connection(stream, &snapshots).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            port,
+            listener_task,
+        })
+    }
+
+    /// The port the stub server listens on, which a sandboxed web server reaches through a forwarded port
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Environment variables that point the web server at the stub instead of the real hosts
+    /// Every external host gets one, including hosts without a recorded response, which the stub answers with 404
+    /// # Arguments
+    ///
+    /// * `urls` - The external URLs of the project
+    pub fn env_vars(&self, urls: &[String]) -> Vec<(String, String)> {
+        external_base_urls(urls)
+            .into_iter()
+            .filter_map(|(env_var, base_url)| {
+                let host: String = Url::parse(&base_url).ok()?.host_str()?.to_string();
+                Some((env_var, format!("http://127.0.0.1:{}/{}", self.port, host)))
+            })
+            .collect()
+    }
+}
+
+/// Stop accepting connections once the stub server is no longer needed
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.listener_task.abort();
+    }
+}
+
+/// Find the recorded response for a request path of the form /<host>/<path>?<query>
+/// The exact path and query is preferred, then the same path with any query
+fn find_snapshot<'a>(snapshots: &'a [UrlSnapshot], request_path: &str) -> Option<&'a UrlSnapshot> {
+    let (host, path_and_query) = request_path.trim_start_matches('/').split_once('/').map_or(
+        (request_path.trim_start_matches('/'), "/".to_string()),
+        |(host, rest)| (host, format!("/{}", rest)),
+    );
+    let path: &str = path_and_query.split('?').next().unwrap_or("/");
+
+    let recorded: Vec<(&UrlSnapshot, Url)> = snapshots
+        .iter()
+        .filter_map(|snapshot| Url::parse(&snapshot.url).ok().map(|url| (snapshot, url)))
+        .filter(|(_, url)| url.host_str() == Some(host))
+        .collect();
+
+    recorded
+        .iter()
+        .find(|(_, url)| {
+            let recorded_path: String = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            recorded_path == path_and_query
+        })
+        .or_else(|| recorded.iter().find(|(_, url)| url.path() == path))
+        .map(|(snapshot, _)| *snapshot)
+}
+
+/// Answer one request with its recorded response, or 404 if nothing was recorded for it
+async fn serve_connection(mut stream: TcpStream, snapshots: &[UrlSnapshot]) -> std::io::Result<()> {
+    let mut request_head: Vec<u8> = vec![];
+    let mut buffer: [u8; 1024] = [0; 1024];
+    while !request_head.windows(4).any(|window| window == b"\r\n\r\n")
+        && request_head.len() < MAX_REQUEST_HEAD_BYTES
+    {
+        let read: usize = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        request_head.extend_from_slice(&buffer[..read]);
+    }
+
+    let request_line: String = String::from_utf8_lossy(&request_head)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let request_path: &str = request_line.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, content_type, body): (u16, String, String) =
+        match find_snapshot(snapshots, request_path) {
+            Some(snapshot) => (
+                snapshot.status,
+                snapshot
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| "application/json".to_string()),
+                snapshot.body.clone(),
+            ),
+            None => (
+                404,
+                "application/json".to_string(),
+                format!(
+                    "{{\"error\": \"no response recorded for {}\"}}",
+                    request_path.replace('"', "")
+                ),
+            ),
+        };
+    let reason: &str = StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+
+    let response: String = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_snapshots() -> Vec<UrlSnapshot> {
+        vec![
+            UrlSnapshot {
+                url: "https://api.example.com/v1/rates?base=USD".to_string(),
+                status: 200,
+                content_type: Some("application/json".to_string()),
+                body: r#"{"base": "USD", "rates": {"EUR": 0.9}}"#.to_string(),
+            },
+            UrlSnapshot {
+                url: "https://api.example.com/v1/rates?base=EUR".to_string(),
+                status: 200,
+                content_type: Some("application/json".to_string()),
+                body: r#"{"base": "EUR", "rates": {"USD": 1.1}}"#.to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn tests_external_base_urls() {
+        assert_eq!(
+            external_base_url_env_var("https://api.example-fx.com/v1/rates"),
+            Some("EXTERNAL_API_EXAMPLE_FX_COM_BASE_URL".to_string())
+        );
+        assert_eq!(external_base_url_env_var("not a url"), None);
+
+        let urls: Vec<String> = test_snapshots()
+            .into_iter()
+            .map(|snapshot| snapshot.url)
+            .collect();
+        assert_eq!(
+            external_base_urls(&urls),
+            vec![(
+                "EXTERNAL_API_EXAMPLE_COM_BASE_URL".to_string(),
+                "https://api.example.com".to_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_stub_server_replays_snapshots() {
+        let stub_server: StubServer = StubServer::start(test_snapshots())
+            .await
+            .expect("Failed to start stub server");
+        let urls: Vec<String> = vec![
+            test_snapshots()[0].url.clone(),
+            "https://api.unrecorded.com/v1/prices".to_string(),
+        ];
+        let env_vars: Vec<(String, String)> = stub_server.env_vars(&urls);
+        assert_eq!(
+            env_vars,
+            vec![
+                (
+                    "EXTERNAL_API_EXAMPLE_COM_BASE_URL".to_string(),
+                    format!("http://127.0.0.1:{}/api.example.com", stub_server.port)
+                ),
+                (
+                    "EXTERNAL_API_UNRECORDED_COM_BASE_URL".to_string(),
+                    format!("http://127.0.0.1:{}/api.unrecorded.com", stub_server.port)
+                ),
+            ]
+        );
+
+        let client: reqwest::Client = reqwest::Client::new();
+        let base_url: &str = &env_vars[0].1;
+        let get = |path: &str| {
+            let request = client.get(format!("{}{}", base_url, path));
+            async move {
+                let response: reqwest::Response = request.send().await.unwrap();
+                (response.status().as_u16(), response.text().await.unwrap())
+            }
+        };
+
+        // The exact query is preferred
+        let (status, body) = get("/v1/rates?base=EUR").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, test_snapshots()[1].body);
+
+        // Other queries fall back to a response for the same path
+        let (status, _) = get("/v1/rates?base=GBP").await;
+        assert_eq!(status, 200);
+
+        let (status, _) = get("/v2/prices").await;
+        assert_eq!(status, 404);
+
+        // Hosts without any recorded response are answered by the stub too, never by the real host
+        let unrecorded: reqwest::Response = client
+            .get(format!("{}/v1/prices", env_vars[1].1))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unrecorded.status().as_u16(), 404);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::helpers::general::save_run_artifact;
+use crate::helpers::stub_server::{read_url_snapshot, UrlSnapshot};
+use crate::models::agents::agent_traits::{AgentError, UrlProfile};
+
+use reqwest::{redirect, Client, Response, StatusCode, Url};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Name of the file in the run output directory that the results of the URL checks are saved to
+const URL_CHECKS_FILE: &str = "url_checks.json";
+
+/// Time allowed for each request to an external URL
+const URL_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Maximum number of redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+/// Maximum number of URLs checked at the same time
+const MAX_CONCURRENT_URL_CHECKS: usize = 4;
+
+/// Maximum number of requests sent to the same host at the same time
+const MAX_REQUESTS_PER_HOST: usize = 1;
+
+/// Pause after each request to a host before the next request to that host is sent
+const HOST_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Attempts made at a URL before it is given up on
+const MAX_URL_CHECK_ATTEMPTS: u8 = 3;
+
+/// Wait before the first retry, doubled for every further retry
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Status codes that mean the URL may work if it is tried again
+const TRANSIENT_STATUS_CODES: [u16; 5] = [429, 500, 502, 503, 504];
+
+/// Number of array items kept in a sample response
+const SAMPLE_ARRAY_ITEMS: usize = 2;
+
+/// Number of characters kept of each string in a sample response
+const SAMPLE_STRING_CHARS: usize = 200;
+
+/// Client for checking external URLs
+/// Redirects are not followed automatically, so profile_url can record them
+pub fn url_check_client() -> Client {
+    Client::builder()
+        .timeout(URL_CHECK_TIMEOUT)
+        .redirect(redirect::Policy::none())
+        .build()
+        .expect("Failed to build URL check client")
+}
+
+/// Infer the shape of a JSON value with Rust type hints, using the first item of each array
+pub fn infer_schema(value: &Value) -> Value {
+    match value {
+        Value::Null => Value::String("Option<serde_json::Value>".to_string()),
+        Value::Bool(_) => Value::String("bool".to_string()),
+        Value::Number(number) if number.is_f64() => Value::String("f64".to_string()),
+        Value::Number(_) => Value::String("i64".to_string()),
+        Value::String(_) => Value::String("String".to_string()),
+        Value::Array(items) => match items.first() {
+            Some(item) => Value::Array(vec![infer_schema(item)]),
+            None => Value::String("Vec<serde_json::Value>".to_string()),
+        },
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, field)| (name.clone(), infer_schema(field)))
+                .collect::<Map<String, Value>>(),
+        ),
+    }
+}
+
+/// Shorten a JSON value so it can be shown to the LLM, keeping the first items of arrays and the start of strings
+pub fn sample_of(value: &Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > SAMPLE_STRING_CHARS => {
+            Value::String(format!(
+                "{}...",
+                text.chars().take(SAMPLE_STRING_CHARS).collect::<String>()
+            ))
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .take(SAMPLE_ARRAY_ITEMS)
+                .map(sample_of)
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, field)| (name.clone(), sample_of(field)))
+                .collect::<Map<String, Value>>(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Call a URL, following up to MAX_REDIRECTS redirects
+/// Returns the final response and the URLs redirected to
+async fn follow_redirects(client: &Client, url: &str) -> Result<(Response, Vec<String>), String> {
+    let mut redirects: Vec<String> = vec![];
+    let mut current_url: Url = Url::parse(url).map_err(|e| e.to_string())?;
+
+    loop {
+        let response: Response = client
+            .get(current_url.clone())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_redirection() || redirects.len() >= MAX_REDIRECTS {
+            return Ok((response, redirects));
+        }
+
+        let location: Option<Url> = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| current_url.join(location).ok());
+        match location {
+            Some(location) => {
+                redirects.push(location.to_string());
+                current_url = location;
+            }
+            None => return Ok((response, redirects)),
+        }
+    }
+}
+
+/// Call an external URL and record what the backend needs to know to use it
+/// Returns the profile, and the response to replay from the stub server if one was received
+/// # Arguments
+///
+/// * `client` - The client making the request (see url_check_client)
+/// * `url` - The URL to profile
+///
+pub async fn profile_url(client: &Client, url: &str) -> (UrlProfile, Option<UrlSnapshot>) {
+    let started: Instant = Instant::now();
+    let mut profile: UrlProfile = UrlProfile {
+        url: url.to_string(),
+        status: None,
+        content_type: None,
+        latency_ms: 0,
+        redirects: vec![],
+        is_auth_required: false,
+        schema: None,
+        sample: None,
+        error: None,
+    };
+
+    let (response, redirects) = match follow_redirects(client, url).await {
+        Ok(response) => response,
+        Err(e) => {
+            profile.latency_ms = started.elapsed().as_millis() as u64;
+            profile.error = Some(e);
+            return (profile, None);
+        }
+    };
+    let status: StatusCode = response.status();
+    profile.status = Some(status.as_u16());
+    profile.redirects = redirects;
+    profile.is_auth_required = [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN].contains(&status);
+
+    let snapshot: UrlSnapshot = match read_url_snapshot(url, response).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            profile.latency_ms = started.elapsed().as_millis() as u64;
+            profile.error = Some(format!("unable to read the response: {}", e));
+            return (profile, None);
+        }
+    };
+    profile.latency_ms = started.elapsed().as_millis() as u64;
+    profile.content_type = snapshot.content_type.clone();
+
+    if let Ok(json) = serde_json::from_str::<Value>(&snapshot.body) {
+        profile.schema = Some(infer_schema(&json));
+        profile.sample = Some(sample_of(&json));
+    }
+
+    (profile, Some(snapshot))
+}
+
+/// Represents the result of checking an external URL
+#[derive(Debug, Serialize, Clone)]
+pub struct UrlCheck {
+    /// What was learned about the URL on the last attempt
+    pub profile: UrlProfile,
+    /// The response to replay from the stub server, if one was received (saved separately, see save_url_snapshots)
+    #[serde(skip)]
+    pub snapshot: Option<UrlSnapshot>,
+    /// Number of attempts made (more than 1 if the URL was retried)
+    pub attempts: u8,
+}
+
+/// Flag to determine if a failed check may pass if it is tried again (no response, rate limited or a server error)
+fn is_transient(profile: &UrlProfile) -> bool {
+    match profile.status {
+        None => true,
+        Some(status) => TRANSIENT_STATUS_CODES.contains(&status),
+    }
+}
+
+/// Check a URL, retrying transient failures with an increasing wait in between
+/// Only MAX_REQUESTS_PER_HOST requests are sent to its host at a time, each followed by HOST_REQUEST_INTERVAL
+async fn check_url_with_retries(
+    client: Client,
+    url: String,
+    host_limit: Arc<Semaphore>,
+) -> UrlCheck {
+    let mut attempts: u8 = 0;
+    loop {
+        attempts += 1;
+        let (profile, snapshot) = {
+            let _host_permit = host_limit.acquire().await;
+            let result = profile_url(&client, &url).await;
+            time::sleep(HOST_REQUEST_INTERVAL).await;
+            result
+        };
+
+        if profile.is_usable() || !is_transient(&profile) || attempts >= MAX_URL_CHECK_ATTEMPTS {
+            return UrlCheck {
+                profile,
+                snapshot,
+                attempts,
+            };
+        }
+        time::sleep(RETRY_BACKOFF * 2u32.pow(attempts as u32 - 1)).await;
+    }
+}
+
+/// Check every URL, MAX_CONCURRENT_URL_CHECKS at a time
+/// Returns the checks in the same order as the URLs
+/// # Arguments
+///
+/// * `urls` - The URLs to check
+/// * `cancel` - Token that is cancelled when the run is interrupted
+///
+pub async fn check_urls(
+    urls: &[String],
+    cancel: &CancellationToken,
+) -> Result<Vec<UrlCheck>, AgentError> {
+    let client: Client = url_check_client();
+    let concurrency_limit: Arc<Semaphore> = Arc::new(Semaphore::new(MAX_CONCURRENT_URL_CHECKS));
+    let mut host_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+    let mut checks: JoinSet<(usize, UrlCheck)> = JoinSet::new();
+
+    for (index, url) in urls.iter().enumerate() {
+        let host: String = Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let host_limit: Arc<Semaphore> = host_limits
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(MAX_REQUESTS_PER_HOST)))
+            .clone();
+        let concurrency_limit: Arc<Semaphore> = concurrency_limit.clone();
+        let client: Client = client.clone();
+        let url: String = url.clone();
+
+        checks.spawn(async move {
+            let _permit = concurrency_limit.acquire_owned().await;
+            (index, check_url_with_retries(client, url, host_limit).await)
+        });
+    }
+
+    let mut results: Vec<(usize, UrlCheck)> = vec![];
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return Err(AgentError::Cancelled),
+            next = checks.join_next() => match next {
+                Some(Ok(result)) => results.push(result),
+                Some(Err(e)) => return Err(AgentError::Failed(format!("URL check failed: {}", e))),
+                None => break,
+            },
+        }
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, check)| check).collect())
+}
+
+/// Save the results of the URL checks in the run output directory
+pub fn save_url_checks(checks: &[UrlCheck]) -> std::io::Result<std::path::PathBuf> {
+    save_run_artifact(URL_CHECKS_FILE, &checks)
+}
+
+/// Describe the checks as a table with a row per URL (result, retries, latency and any problem)
+pub fn url_check_summary(checks: &[UrlCheck]) -> String {
+    let url_width: usize = checks
+        .iter()
+        .map(|check| check.profile.url.len())
+        .max()
+        .unwrap_or(0)
+        .max("URL".len());
+
+    let mut summary: String = format!(
+        "{:<url_width$}  {:<6}  {:<7}  {:<9}  {}",
+        "URL", "RESULT", "RETRIES", "LATENCY", "PROBLEM"
+    );
+    for check in checks {
+        let result: &str = if check.profile.is_usable() {
+            "PASS"
+        } else {
+            "FAIL"
+        };
+        summary.push_str(&format!(
+            "\n{:<url_width$}  {:<6}  {:<7}  {:<9}  {}",
+            check.profile.url,
+            result,
+            check.attempts.saturating_sub(1),
+            format!("{}ms", check.profile.latency_ms),
+            check.profile.problem().unwrap_or_default()
+        ));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::stub_server::StubServer;
+    use serde_json::json;
+
+    #[test]
+    fn tests_infer_schema_and_sample() {
+        let response: Value = json!({
+            "base": "USD",
+            "updated": null,
+            "rates": [
+                {"symbol": "EUR", "rate": 0.92, "volume": 1200},
+                {"symbol": "GBP", "rate": 0.79, "volume": 800},
+                {"symbol": "JPY", "rate": 151.2, "volume": 400}
+            ],
+            "tags": []
+        });
+
+        assert_eq!(
+            infer_schema(&response),
+            json!({
+                "base": "String",
+                "updated": "Option<serde_json::Value>",
+                "rates": [{"symbol": "String", "rate": "f64", "volume": "i64"}],
+                "tags": "Vec<serde_json::Value>"
+            })
+        );
+
+        let sample: Value = sample_of(&response);
+        assert_eq!(sample["rates"].as_array().map(Vec::len), Some(2));
+        assert_eq!(sample["base"], "USD");
+
+        let long_text: Value = Value::String("x".repeat(500));
+        assert_eq!(
+            sample_of(&long_text).as_str().map(str::len),
+            Some(SAMPLE_STRING_CHARS + 3)
+        );
+    }
+
+    #[test]
+    fn tests_url_profile_problem() {
+        let mut profile: UrlProfile = UrlProfile {
+            url: "https://api.example.com/v1/rates".to_string(),
+            status: Some(401),
+            content_type: None,
+            latency_ms: 12,
+            redirects: vec![],
+            is_auth_required: true,
+            schema: None,
+            sample: None,
+            error: None,
+        };
+        assert!(!profile.is_usable());
+        assert_eq!(
+            profile.problem(),
+            Some("requires authentication (status 401)".to_string())
+        );
+
+        profile.status = Some(200);
+        profile.is_auth_required = false;
+        assert!(profile.is_usable());
+        assert_eq!(profile.problem(), None);
+    }
+
+    #[tokio::test]
+    async fn tests_profile_url() {
+        let stub_server: StubServer = StubServer::start(vec![UrlSnapshot {
+            url: "https://api.example.com/v1/rates".to_string(),
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: r#"{"base": "USD", "rates": {"EUR": 0.92}}"#.to_string(),
+        }])
+        .await
+        .expect("Failed to start stub server");
+        let base_url: String = format!("http://127.0.0.1:{}/api.example.com", stub_server.port());
+        let url: String = format!("{}/v1/rates", base_url);
+
+        let (profile, snapshot) = profile_url(&url_check_client(), &url).await;
+        assert!(profile.is_usable());
+        assert_eq!(profile.content_type.as_deref(), Some("application/json"));
+        assert_eq!(
+            profile.schema,
+            Some(json!({"base": "String", "rates": {"EUR": "f64"}}))
+        );
+        assert_eq!(snapshot.map(|snapshot| snapshot.url), Some(url));
+
+        let (missing, _) =
+            profile_url(&url_check_client(), &format!("{}/v2/prices", base_url)).await;
+        assert_eq!(
+            missing.problem(),
+            Some("responded with status 404".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_check_urls_retries_transient_failures() {
+        let snapshot = |path: &str, status: u16| UrlSnapshot {
+            url: format!("https://api.example.com{}", path),
+            status,
+            content_type: Some("application/json".to_string()),
+            body: "{}".to_string(),
+        };
+        let stub_server: StubServer = StubServer::start(vec![
+            snapshot("/v1/rates", 200),
+            snapshot("/v1/missing", 404),
+            snapshot("/v1/busy", 503),
+        ])
+        .await
+        .expect("Failed to start stub server");
+        let base_url: String = format!("http://127.0.0.1:{}/api.example.com", stub_server.port());
+        let urls: Vec<String> = ["/v1/rates", "/v1/missing", "/v1/busy"]
+            .iter()
+            .map(|path| format!("{}{}", base_url, path))
+            .collect();
+
+        let checks: Vec<UrlCheck> = check_urls(&urls, &CancellationToken::new())
+            .await
+            .expect("URL checks should not fail");
+        let attempts: Vec<(bool, u8)> = checks
+            .iter()
+            .map(|check| (check.profile.is_usable(), check.attempts))
+            .collect();
+        assert_eq!(
+            attempts,
+            vec![(true, 1), (false, 1), (false, MAX_URL_CHECK_ATTEMPTS)]
+        );
+
+        let summary: String = url_check_summary(&checks);
+        assert_eq!(summary.lines().count(), 4);
+        assert!(summary.lines().nth(3).unwrap().contains("FAIL    2 "));
+        assert!(summary.contains("responded with status 503"));
+    }
+}
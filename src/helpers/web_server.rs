@@ -0,0 +1,448 @@
+use crate::helpers::general::run_output_path;
+use crate::helpers::sandbox::{
+    is_network_allowed, is_sandbox_available, sandboxed_command, toolchain_env, PortForward,
+    SandboxBridge, SandboxConfig, SandboxMode, SandboxPhase,
+};
+use crate::models::agents::agent_traits::AgentError;
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Name of the integration test target written into the web server project (tests/api_tests.rs)
+pub const INTEGRATION_TEST_TARGET: &str = "api_tests";
+
+/// Seconds to wait for the web server to accept connections when SERVER_READY_TIMEOUT_SECS is not set
+const DEFAULT_SERVER_READY_TIMEOUT_SECS: u64 = 60;
+
+/// How often the web server is checked while waiting for it to start
+const READINESS_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Name of the file in the run output directory that the web server output is appended to
+const SERVER_LOG_FILE: &str = "server.log";
+
+/// Represents the output of a running web server
+/// stdout and stderr are read in the background, so the server never blocks on a full pipe
+#[derive(Debug)]
+pub struct ServerLog {
+    /// Every line the server has printed so far, prefixed with the stream it was printed to
+    lines: Arc<Mutex<Vec<String>>>,
+    /// Tasks reading stdout and stderr, which finish once the server closes them
+    drains: Vec<JoinHandle<()>>,
+}
+
+/// Implementation for the output of a running web server
+impl ServerLog {
+    /// Start reading the stdout and stderr of a server, appending every line to the log file
+    pub fn start(server: &mut Child, log_path: &Path) -> std::io::Result<Self> {
+        let mut log_file: File = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_path)?;
+        writeln!(log_file, "--- Server started (pid {:?}) ---", server.id())?;
+
+        let log_file: Arc<Mutex<File>> = Arc::new(Mutex::new(log_file));
+        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let mut drains: Vec<JoinHandle<()>> = vec![];
+        if let Some(stdout) = server.stdout.take() {
+            drains.push(drain_lines(stdout, "stdout", &lines, &log_file));
+        }
+        if let Some(stderr) = server.stderr.take() {
+            drains.push(drain_lines(stderr, "stderr", &lines, &log_file));
+        }
+
+        Ok(Self { lines, drains })
+    }
+
+    /// Number of lines printed so far, used to find the lines printed after a point in time
+    pub fn mark(&self) -> usize {
+        self.lines.lock().unwrap().len()
+    }
+
+    /// Every line printed since the mark was taken
+    pub fn lines_since(&self, mark: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .get(mark..)
+            .map(<[String]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    /// Wait until every line has been read, once the server has exited
+    pub async fn closed(&mut self) {
+        for drain in self.drains.drain(..) {
+            let _ = drain.await;
+        }
+    }
+}
+
+/// Read a stream of the server line by line into the shared lines and the log file
+fn drain_lines<R: AsyncRead + Unpin + Send + 'static>(
+    pipe: R,
+    stream: &'static str,
+    lines: &Arc<Mutex<Vec<String>>>,
+    log_file: &Arc<Mutex<File>>,
+) -> JoinHandle<()> {
+    let lines: Arc<Mutex<Vec<String>>> = Arc::clone(lines);
+    let log_file: Arc<Mutex<File>> = Arc::clone(log_file);
+
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(pipe).lines();
+        while let Ok(Some(line)) = reader.next_line().await {
+            let line: String = format!("[{}] {}", stream, line);
+            if let Ok(mut log_file) = log_file.lock() {
+                let _ = writeln!(log_file, "{}", line);
+            }
+            lines.lock().unwrap().push(line);
+        }
+    })
+}
+
+/// Find the lines of server output that report a runtime failure
+/// Panics are kept with the line after them, which holds the panic message
+pub fn find_runtime_errors(lines: &[String]) -> Vec<String> {
+    let mut errors: Vec<String> = vec![];
+
+    for (index, line) in lines.iter().enumerate() {
+        let is_error: bool = line.contains(" ERROR ")
+            || line.contains("[ERROR")
+            || line.to_lowercase().contains("error:");
+        if line.contains("panicked at") {
+            errors.push(line.clone());
+            if let Some(message) = lines.get(index + 1) {
+                errors.push(message.clone());
+            }
+        } else if is_error && errors.last() != Some(line) {
+            errors.push(line.clone());
+        }
+    }
+
+    errors
+}
+
+/// Path of the generated web server project
+pub fn web_server_project_path() -> PathBuf {
+    let web_server_project_path: String = env::var("WEB_SERVER_PROJECT_PATH")
+        .expect("WEB_SERVER_PROJECT_PATH not found in environment variables");
+
+    PathBuf::from(web_server_project_path)
+}
+
+/// Create a cargo command in the web server project, run in the sandbox if the phase is sandboxed
+/// In the sandbox cargo only gets the toolchain environment and the given variables, never the rest of the agent's environment
+/// Returns the command, and the bridge forwarding the ports if the sandbox has no network, which must outlive the command
+/// # Arguments
+///
+/// * `args` - The arguments passed to cargo (i.e. ["build"])
+/// * `envs` - Further environment variables of the command (i.e. PORT)
+/// * `phase` - Whether the command builds or runs the code, or None if it never runs generated code
+/// * `forwards` - Loopback ports the command must reach, or be reached on, across the sandbox boundary
+fn cargo_command(
+    args: &[&str],
+    envs: &[(String, String)],
+    phase: Option<SandboxPhase>,
+    forwards: &[PortForward],
+) -> Result<(Command, Option<SandboxBridge>), String> {
+    let project_path: PathBuf = web_server_project_path();
+
+    let (mut command, bridge): (Command, Option<SandboxBridge>) = match phase {
+        Some(phase) if SandboxMode::from_env().is_sandboxed(phase) => {
+            if !is_sandbox_available() {
+                return Err(
+                    "The sandbox needs bubblewrap (bwrap) installed. Install it or set SANDBOX=off"
+                        .to_string(),
+                );
+            }
+
+            // The project is bound into the sandbox, which needs an absolute path
+            let project_path: PathBuf =
+                fs::canonicalize(&project_path).unwrap_or(project_path.clone());
+            let config: SandboxConfig = SandboxConfig::from_env(phase, &project_path);
+            let bridge: Option<SandboxBridge> = if config.allow_network || forwards.is_empty() {
+                None
+            } else {
+                Some(
+                    SandboxBridge::start(forwards)
+                        .map_err(|e| format!("Failed to forward ports into the sandbox: {}", e))?,
+                )
+            };
+            let mut command_envs: Vec<(String, String)> = toolchain_env();
+            command_envs.extend(envs.iter().cloned());
+            let (program, sandbox_args) = sandboxed_command(
+                "cargo",
+                args,
+                &command_envs,
+                &project_path,
+                &config,
+                bridge.as_ref(),
+            );
+            // bubblewrap gets the same environment, which it clears again inside the sandbox
+            let mut command: Command = Command::new(program);
+            command.args(sandbox_args).env_clear().envs(command_envs);
+            (command, bridge)
+        }
+        // Without the sandbox cargo runs as the user would run it, with their full environment
+        _ => {
+            let mut command: Command = Command::new("cargo");
+            command.args(args).envs(envs.iter().cloned());
+            (command, None)
+        }
+    };
+
+    command
+        .current_dir(project_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    Ok((command, bridge))
+}
+
+/// Wait for the output of a command, killing it if the run is cancelled while it is in progress
+async fn command_output(
+    mut command: Command,
+    args: &[&str],
+    cancel: &CancellationToken,
+) -> Result<Output, AgentError> {
+    tokio::select! {
+        output = command.output() => output.map_err(|e| AgentError::Failed(format!("Failed to run cargo {}: {}", args.join(" "), e))),
+        _ = cancel.cancelled() => Err(AgentError::Cancelled),
+    }
+}
+
+/// Run a cargo command that builds the web server project and wait for its output
+/// # Arguments
+///
+/// * `args` - The arguments passed to cargo (i.e. ["build"])
+/// * `cancel` - Token that is cancelled when the run is interrupted
+pub async fn run_cargo(args: &[&str], cancel: &CancellationToken) -> Result<Output, AgentError> {
+    run_cargo_with_env(args, &[], Some(SandboxPhase::Build), &[], cancel).await
+}
+
+/// Run a cargo command in the web server project with extra environment variables
+/// # Arguments
+///
+/// * `args` - The arguments passed to cargo (i.e. ["test"])
+/// * `envs` - Environment variables set for the command (i.e. [("API_BASE_URL", "http://localhost:8080")])
+/// * `phase` - Whether the command builds or runs the code, which decides how it is sandboxed
+///   None for commands that never run generated code (i.e. cargo add), which are never sandboxed
+/// * `forwards` - Loopback ports on the host the command reaches (i.e. the port of the web server under test)
+/// * `cancel` - Token that is cancelled when the run is interrupted
+pub async fn run_cargo_with_env(
+    args: &[&str],
+    envs: &[(&str, &str)],
+    phase: Option<SandboxPhase>,
+    forwards: &[PortForward],
+    cancel: &CancellationToken,
+) -> Result<Output, AgentError> {
+    // A sandbox without network cannot download crates, so they are downloaded before it starts
+    if let Some(phase) = phase {
+        let is_offline_sandbox: bool =
+            SandboxMode::from_env().is_sandboxed(phase) && !is_network_allowed(phase);
+        if is_offline_sandbox {
+            let fetch_args: [&str; 1] = ["fetch"];
+            let (fetch_command, _) =
+                cargo_command(&fetch_args, &[], None, &[]).map_err(AgentError::Failed)?;
+            let fetch_output: Output = command_output(fetch_command, &fetch_args, cancel).await?;
+            if !fetch_output.status.success() {
+                return Ok(fetch_output);
+            }
+        }
+    }
+
+    let envs: Vec<(String, String)> = envs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    let (command, _bridge) =
+        cargo_command(args, &envs, phase, forwards).map_err(AgentError::Failed)?;
+    command_output(command, args, cancel).await
+}
+
+/// Find a free port for the web server by letting the OS pick one
+pub fn allocate_port() -> std::io::Result<u16> {
+    let listener: TcpListener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+/// Address of a web server listening on the port (i.e. http://localhost:8080)
+pub fn server_base_url(port: u16) -> String {
+    format!("http://localhost:{}", port)
+}
+
+/// Read how long to wait for the web server to start from SERVER_READY_TIMEOUT_SECS
+pub fn server_ready_timeout() -> Duration {
+    let seconds: u64 = env::var("SERVER_READY_TIMEOUT_SECS")
+        .ok()
+        .and_then(|seconds| seconds.trim().parse().ok())
+        .unwrap_or(DEFAULT_SERVER_READY_TIMEOUT_SECS);
+    Duration::from_secs(seconds)
+}
+
+/// Start the web server in the background, listening on the port given in the PORT environment variable
+/// Its output is appended to server.log in the run output directory
+/// The server is killed when the child is dropped, so cancelling the run never orphans it
+/// Returns the server, its output, and the bridge forwarding its ports if it runs in a sandbox without network,
+/// which must be kept until the server is stopped
+/// # Arguments
+///
+/// * `port` - The port the server listens on
+/// * `envs` - Further environment variables of the server (i.e. the base URLs of the stub server)
+/// * `outbound_ports` - Loopback ports on the host the server calls (i.e. the port of the stub server)
+pub fn spawn_web_server(
+    port: u16,
+    envs: &[(String, String)],
+    outbound_ports: &[u16],
+) -> std::io::Result<(Child, ServerLog, Option<SandboxBridge>)> {
+    let mut server_envs: Vec<(String, String)> = vec![("PORT".to_string(), port.to_string())];
+    server_envs.extend(envs.iter().cloned());
+    let mut forwards: Vec<PortForward> = vec![PortForward::Inbound(port)];
+    forwards.extend(
+        outbound_ports
+            .iter()
+            .map(|port| PortForward::Outbound(*port)),
+    );
+
+    let (mut command, bridge) =
+        cargo_command(&["run"], &server_envs, Some(SandboxPhase::Run), &forwards)
+            .map_err(std::io::Error::other)?;
+    let mut server: Child = command.spawn()?;
+
+    let server_log: ServerLog =
+        ServerLog::start(&mut server, &run_output_path().join(SERVER_LOG_FILE))?;
+    Ok((server, server_log, bridge))
+}
+
+/// Describe why a server stopped, with everything it printed
+async fn describe_exited_server(server_log: &mut ServerLog, exit_status: ExitStatus) -> String {
+    server_log.closed().await;
+
+    format!(
+        "Server exited on start up with {} \n SERVER_OUTPUT: {}",
+        exit_status,
+        server_log.lines_since(0).join("\n")
+    )
+}
+
+/// Wait until the web server accepts connections on its port
+/// Fails with everything the server printed if it exits first, or once the timeout has passed
+/// # Arguments
+///
+/// * `server` - The running web server
+/// * `server_log` - The output of the web server
+/// * `port` - The port the server was told to listen on
+/// * `timeout` - How long to wait before giving up
+/// * `cancel` - Token that is cancelled when the run is interrupted
+pub async fn wait_for_server(
+    server: &mut Child,
+    server_log: &mut ServerLog,
+    port: u16,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> Result<(), AgentError> {
+    let deadline: Instant = Instant::now() + timeout;
+
+    loop {
+        if let Some(exit_status) = server
+            .try_wait()
+            .map_err(|e| AgentError::Failed(format!("Failed to check web server: {}", e)))?
+        {
+            return Err(AgentError::Failed(
+                describe_exited_server(server_log, exit_status).await,
+            ));
+        }
+
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(AgentError::Failed(format!(
+                "Server did not accept connections on port {} within {} seconds. It must listen on the port in the PORT environment variable",
+                port,
+                timeout.as_secs()
+            )));
+        }
+
+        tokio::select! {
+            _ = time::sleep(READINESS_POLL_INTERVAL) => {}
+            _ = cancel.cancelled() => return Err(AgentError::Cancelled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Start a shell script as a stand in for the web server, logging to a temporary file
+    fn start_script(script: &str, log_path: &Path) -> (Child, ServerLog) {
+        let mut server: Child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let server_log: ServerLog = ServerLog::start(&mut server, log_path).unwrap();
+        (server, server_log)
+    }
+
+    #[tokio::test]
+    async fn tests_wait_for_server() {
+        let cancel: CancellationToken = CancellationToken::new();
+        let timeout: Duration = Duration::from_secs(5);
+        let log_path: PathBuf = env::temp_dir().join(format!("server_{}.log", std::process::id()));
+
+        // A process that exits is reported with its output, which is also written to the log file
+        let (mut exited, mut exited_log) =
+            start_script("echo starting; echo port in use >&2; exit 3", &log_path);
+        let port: u16 = allocate_port().unwrap();
+        match wait_for_server(&mut exited, &mut exited_log, port, timeout, &cancel).await {
+            Err(AgentError::Failed(msg)) => {
+                assert!(msg.contains("[stdout] starting"));
+                assert!(msg.contains("[stderr] port in use"));
+            }
+            other => panic!("Expected the server to have exited, got {:?}", other),
+        }
+        let log_contents: String = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log_contents.contains("[stderr] port in use"));
+        std::fs::remove_file(&log_path).unwrap();
+
+        // A process listening on the port is ready
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port: u16 = listener.local_addr().unwrap().port();
+        let (mut listening, mut listening_log) = start_script("sleep 5", &log_path);
+        assert!(
+            wait_for_server(&mut listening, &mut listening_log, port, timeout, &cancel)
+                .await
+                .is_ok()
+        );
+        std::fs::remove_file(&log_path).unwrap();
+    }
+
+    #[test]
+    fn tests_find_runtime_errors() {
+        let lines: Vec<String> = vec![
+            "[stdout] Server listening on 8080".to_string(),
+            "[stderr] thread 'actix-rt|system:0|arbiter:0' panicked at src/handlers.rs:20:5:"
+                .to_string(),
+            "[stderr] called `Option::unwrap()` on a `None` value".to_string(),
+            "[stderr] [2024-01-01T00:00:00Z ERROR actix_web] Error: database locked".to_string(),
+            "[stdout] GET /item 200".to_string(),
+        ];
+
+        assert_eq!(find_runtime_errors(&lines), lines[1..4].to_vec());
+        assert!(find_runtime_errors(&lines[..1]).is_empty());
+    }
+}
@@ -12,19 +12,57 @@ mod apis;
 mod helpers;
 mod models;
 
-use helpers::command_line::get_user_response;
+use helpers::command_line::{get_user_response, spawn_ctrl_c_handler};
+use helpers::sandbox::run_relay;
+use models::agents::agent_traits::AgentError;
 use models::agents_manager::managing_agent::ManagingAgent;
+use tokio_util::sync::CancellationToken;
+
+/// Exit code used when the run is cancelled with Ctrl-C
+const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// Exit code used when the user aborts the project at an approval gate
+const ABORTED_EXIT_CODE: i32 = 1;
+
+/// Print why the run stopped early and exit with a matching exit code
+fn exit_with_error(e: Box<dyn std::error::Error>) -> ! {
+    println!("{}", e);
+    match e.downcast_ref::<AgentError>() {
+        Some(AgentError::Cancelled) => std::process::exit(CANCELLED_EXIT_CODE),
+        _ => std::process::exit(ABORTED_EXIT_CODE),
+    }
+}
 
 /// Main function used to execute agents and start building a website
 #[tokio::main]
 async fn main() {
-    let user_request: String = get_user_response("What website would you like to build today?");
+    // Inside the sandbox this binary only relays the ports of the generated code it runs
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(exit_code) = run_relay(&args).await {
+        std::process::exit(exit_code);
+    }
+
+    let cancel: CancellationToken = CancellationToken::new();
+    spawn_ctrl_c_handler(cancel.clone());
 
-    let mut manage_agent: ManagingAgent = ManagingAgent::new(user_request)
-        .await
-        .expect("Error creating agent");
+    let user_request: String =
+        match get_user_response("What website would you like to build today?", &cancel).await {
+            Ok(user_request) => user_request,
+            Err(_) => {
+                println!("Run cancelled before the project started");
+                std::process::exit(CANCELLED_EXIT_CODE);
+            }
+        };
 
-    manage_agent.execute_project().await;
+    let mut manage_agent: ManagingAgent = tokio::select! {
+        agent = ManagingAgent::new(user_request, &cancel) => agent.unwrap_or_else(|e| exit_with_error(e)),
+        _ = cancel.cancelled() => {
+            println!("Run cancelled before the project started");
+            std::process::exit(CANCELLED_EXIT_CODE);
+        }
+    };
 
-    dbg!(manage_agent);
+    if let Err(e) = manage_agent.execute_project(&cancel).await {
+        exit_with_error(e);
+    }
 }
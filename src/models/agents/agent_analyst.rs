@@ -0,0 +1,220 @@
+use crate::ai_functions::aifunc_analyst::print_clarifying_questions;
+use crate::helpers::command_line::{get_user_response, ApprovalPolicy, PrintCommand};
+use crate::helpers::general::ai_task_request_decoded;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, FactSheet, Requirement, SpecialFunctions};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of follow-up questions asked about the user's request
+const MAX_QUESTIONS: usize = 3;
+
+/// Represents a question about an ambiguity in the user's request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ClarifyingQuestion {
+    /// What the question is about (i.e. data_source, auth, persistence)
+    pub topic: String,
+    /// The question to ask the user
+    pub question: String,
+    /// The answer to assume if the user cannot be asked
+    pub assumption: String,
+}
+
+/// Represents a Requirements Analyst agent
+#[derive(Debug)]
+pub struct AgentRequirementsAnalyst {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+    /// The original request made by the user
+    user_request: String,
+}
+
+/// Implementation for a Requirements Analyst agent
+impl AgentRequirementsAnalyst {
+    /// Create a new Requirements Analyst agent
+    pub fn new(user_request: String) -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Clarifies ambiguities in the user's request before work starts".to_string(),
+            position: "Requirements Analyst".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self {
+            attributes,
+            user_request,
+        }
+    }
+
+    /// Find the ambiguities in the user's request that need clarifying
+    async fn call_clarifying_questions(
+        &self,
+        factsheet: &FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<ClarifyingQuestion>, AgentError> {
+        let msg_context: String = format!(
+            "USER_REQUEST: {} \n PROJECT_DESCRIPTION: {} \n",
+            self.user_request, factsheet.project_description
+        );
+
+        ai_task_request_decoded::<Vec<ClarifyingQuestion>>(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_clarifying_questions),
+            print_clarifying_questions,
+            cancel,
+        )
+        .await
+    }
+}
+
+/// Ask the user each question unless the run is non-interactive, returning the answers in order
+/// Returns AgentError::Cancelled if the run is cancelled while waiting on the user
+async fn ask_questions(
+    questions: &[ClarifyingQuestion],
+    policy: ApprovalPolicy,
+    cancel: &CancellationToken,
+) -> Result<Vec<String>, AgentError> {
+    let mut answers: Vec<String> = vec![];
+    if policy == ApprovalPolicy::AutoApprove {
+        return Ok(answers);
+    }
+
+    for question in questions.iter().take(MAX_QUESTIONS) {
+        let prompt: String = format!(
+            "{} (press Enter to assume: {})",
+            question.question, question.assumption
+        );
+        answers.push(get_user_response(&prompt, cancel).await?);
+    }
+    Ok(answers)
+}
+
+/// Turn questions into requirements using the user's answers
+/// An empty or missing answer keeps the assumption, so the user can skip any question
+/// # Arguments
+///
+/// * `questions` - The questions to answer, most important first
+/// * `answers` - The user's answers in the same order, empty if the run is non-interactive
+fn clarify_requirements(questions: &[ClarifyingQuestion], answers: &[String]) -> Vec<Requirement> {
+    questions
+        .iter()
+        .take(MAX_QUESTIONS)
+        .enumerate()
+        .map(|(index, question)| {
+            let answer: &str = answers.get(index).map(String::as_str).unwrap_or_default();
+            let is_assumption: bool = answer.trim().is_empty();
+
+            Requirement {
+                topic: question.topic.clone(),
+                question: question.question.clone(),
+                answer: if is_assumption {
+                    question.assumption.clone()
+                } else {
+                    answer.trim().to_string()
+                },
+                is_assumption,
+            }
+        })
+        .collect()
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentRequirementsAnalyst {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    /// Continue with whatever the later agents assume about the request
+    fn fill_defaults(&self, factsheet: &mut FactSheet) {
+        if factsheet.requirements.is_none() {
+            factsheet.requirements = Some(vec![]);
+        }
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    let questions: Vec<ClarifyingQuestion> =
+                        self.call_clarifying_questions(factsheet, cancel).await?;
+
+                    let answers: Vec<String> =
+                        ask_questions(&questions, ApprovalPolicy::from_env(), cancel).await?;
+                    let requirements: Vec<Requirement> = clarify_requirements(&questions, &answers);
+
+                    for requirement in &requirements {
+                        let requirement_msg: String = format!(
+                            "{} - {}{}",
+                            requirement.topic,
+                            requirement.answer,
+                            if requirement.is_assumption {
+                                " (assumed)"
+                            } else {
+                                ""
+                            }
+                        );
+                        PrintCommand::AICall
+                            .print_agent_message(&self.attributes.position, &requirement_msg);
+                    }
+
+                    factsheet.requirements = Some(requirements);
+                    self.attributes.state = AgentState::Finished;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(topic: &str) -> ClarifyingQuestion {
+        ClarifyingQuestion {
+            topic: topic.to_string(),
+            question: format!("Which {}?", topic),
+            assumption: format!("Simplest {}", topic),
+        }
+    }
+
+    #[test]
+    fn tests_clarify_requirements() {
+        let questions: Vec<ClarifyingQuestion> = vec![
+            question("auth"),
+            question("persistence"),
+            question("data_source"),
+            question("features"),
+        ];
+
+        // Non-interactive runs record assumptions without asking anything
+        let assumed: Vec<Requirement> = clarify_requirements(&questions, &[]);
+        assert_eq!(assumed.len(), MAX_QUESTIONS);
+        assert!(assumed.iter().all(|requirement| requirement.is_assumption));
+        assert_eq!(assumed[0].answer, "Simplest auth");
+
+        // Interactive runs keep the assumption only when the user skips the question
+        let answers: Vec<String> = vec!["".to_string(), " Username and password ".to_string()];
+        let answered: Vec<Requirement> = clarify_requirements(&questions[..2], &answers);
+        assert!(answered[0].is_assumption);
+        assert_eq!(answered[1].answer, "Username and password");
+        assert!(!answered[1].is_assumption);
+    }
+}
@@ -1,19 +1,33 @@
 use crate::ai_functions::aifunc_architect::{print_project_scope, print_site_urls};
-use crate::helpers::command_line::PrintCommand;
-use crate::helpers::general::{ai_task_request_decoded, check_status_code};
+use crate::helpers::command_line::{
+    request_approval, ApprovalDecision, ApprovalGate, PrintCommand,
+};
+use crate::helpers::general::ai_task_request_decoded;
+use crate::helpers::stub_server::{save_url_snapshots, UrlSnapshot};
+use crate::helpers::url_profile::{check_urls, save_url_checks, url_check_summary, UrlCheck};
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
 use crate::models::agent_basic::basic_traits::BasicTraits;
-use crate::models::agents::agent_traits::{FactSheet, ProjectScope, SpecialFunctions};
+use crate::models::agents::agent_traits::{
+    AgentError, Escalation, FactSheet, ProjectScope, SpecialFunctions, UrlProfile,
+};
 
 use async_trait::async_trait;
-use reqwest::Client;
-use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of times replacements are asked for when too few external URLs can be used
+const MAX_URL_REPLACEMENT_ROUNDS: u8 = 2;
 
 /// Represents a Solutions Architect agent
 #[derive(Debug)]
 pub struct AgentSolutionArchitect {
     /// Attributes that are specific to a Solutions Architect agent
     attributes: BasicAgent,
+    /// External URLs that a later agent could not use and that need replacing
+    rejected_urls: Vec<String>,
+    /// Number of times replacements have been asked for because too few URLs could be used
+    url_replacement_rounds: u8,
+    /// Results of every URL check made, across all replacement rounds
+    url_checks: Vec<UrlCheck>,
 }
 
 /// Implementation for a Solutions Architect agent
@@ -27,24 +41,42 @@ impl AgentSolutionArchitect {
             memory: vec![],
         };
 
-        Self { attributes }
+        Self {
+            attributes,
+            rejected_urls: vec![],
+            url_replacement_rounds: 0,
+            url_checks: vec![],
+        }
     }
 
     /// Retrieve Project Scope
-    async fn call_project_scope(&mut self, factsheet: &mut FactSheet) -> ProjectScope {
-        let msg_context: String = format!("{}", factsheet.project_description);
+    async fn call_project_scope(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<ProjectScope, AgentError> {
+        let mut msg_context: String = format!("{}", factsheet.project_description);
+
+        // Answers from the user decide the scope where the description is ambiguous
+        if let Some(requirements) = &factsheet.requirements {
+            msg_context.push_str(&format!(
+                " \n REQUIREMENTS: {}",
+                serde_json::to_string(requirements)?
+            ));
+        }
 
         let ai_response: ProjectScope = ai_task_request_decoded::<ProjectScope>(
             msg_context,
             &self.attributes.position,
             get_function_string!(print_project_scope),
             print_project_scope,
+            cancel,
         )
-        .await;
+        .await?;
 
         factsheet.project_scope = Some(ai_response.clone());
         self.attributes.update_state(AgentState::Finished);
-        return ai_response;
+        return Ok(ai_response);
     }
 
     // Determine any external URLS that will be needed for the project scope
@@ -52,20 +84,45 @@ impl AgentSolutionArchitect {
         &mut self,
         factsheet: &mut FactSheet,
         msg_context: String,
-    ) {
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
         let ai_response: Vec<String> = ai_task_request_decoded::<Vec<String>>(
             msg_context,
             &self.attributes.position,
             get_function_string!(print_site_urls),
             print_site_urls,
+            cancel,
         )
-        .await;
+        .await?;
 
         factsheet.external_urls = Some(ai_response);
         self.attributes.state = AgentState::UnitTesting;
+        Ok(())
+    }
+
+    /// Check whether a URL already passed a check in an earlier round
+    fn is_url_usable(&self, url: &str) -> bool {
+        self.url_checks
+            .iter()
+            .any(|check| check.profile.url == url && check.profile.is_usable())
     }
 }
 
+/// Apply user edits to a list of URLs
+/// Each edit is either +url to add a URL or -url to remove one
+fn apply_url_edits(mut urls: Vec<String>, edits: &str) -> Vec<String> {
+    for edit in edits.split(|c: char| c.is_whitespace() || c == ',') {
+        if let Some(url) = edit.strip_prefix('+') {
+            if !url.is_empty() && !urls.iter().any(|existing| existing == url) {
+                urls.push(url.to_string());
+            }
+        } else if let Some(url) = edit.strip_prefix('-') {
+            urls.retain(|existing| existing != url);
+        }
+    }
+    urls
+}
+
 /// Implementation of SpecialFunctions for a AgentsSolutionsArchitect
 #[async_trait]
 impl SpecialFunctions for AgentSolutionArchitect {
@@ -74,58 +131,208 @@ impl SpecialFunctions for AgentSolutionArchitect {
         &self.attributes
     }
 
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    /// Remember which URLs were rejected so replacements can be suggested
+    fn receive_escalation(&mut self, escalation: &Escalation) {
+        self.rejected_urls
+            .extend(escalation.rejected_urls.iter().cloned());
+        self.attributes.update_state(AgentState::Discovery);
+    }
+
+    /// Fall back to a CRUD only project without external URLs
+    fn fill_defaults(&self, factsheet: &mut FactSheet) {
+        if factsheet.project_scope.is_none() {
+            factsheet.project_scope = Some(ProjectScope {
+                is_crud_required: true,
+                is_user_login_and_logout: false,
+                is_external_urls_required: false,
+                ..Default::default()
+            });
+        }
+        if factsheet.external_urls.is_none() {
+            factsheet.external_urls = Some(vec![]);
+        }
+    }
+
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
     ) -> Result<(), Box<dyn std::error::Error>> {
         // !!! WARNING - BE CAREFUL OF INFINITE LOOPS - THIS CAN LEAD TO ADDITIONAL COSTS !!!
         while self.attributes.state != AgentState::Finished {
             match self.attributes.state {
                 AgentState::Discovery => {
-                    let project_scope: ProjectScope = self.call_project_scope(factsheet).await;
+                    // Replace URLs that a later agent could not use, keeping the approved scope
+                    if !self.rejected_urls.is_empty() && factsheet.project_scope.is_some() {
+                        let rejected_urls: Vec<String> = std::mem::take(&mut self.rejected_urls);
+                        let mut urls: Vec<String> =
+                            factsheet.external_urls.take().unwrap_or_default();
+                        urls.retain(|url| !rejected_urls.contains(url));
+                        if let Some(profiles) = factsheet.external_url_profiles.as_mut() {
+                            profiles.retain(|profile| !rejected_urls.contains(&profile.url));
+                        }
+
+                        let msg_context: String = format!(
+                            "{} \n DO NOT USE THESE URLS: {:?}",
+                            factsheet.project_description, rejected_urls
+                        );
+                        self.call_determine_external_urls(factsheet, msg_context, cancel)
+                            .await?;
+
+                        // Keep the URLs that already passed and add the new suggestions after them
+                        for url in factsheet.external_urls.take().unwrap_or_default() {
+                            if !rejected_urls.contains(&url) && !urls.contains(&url) {
+                                urls.push(url);
+                            }
+                        }
+                        factsheet.external_urls = Some(urls);
+                        continue;
+                    }
+
+                    let mut project_scope: ProjectScope =
+                        self.call_project_scope(factsheet, cancel).await?;
+
+                    // Let the user review the scope flags
+                    loop {
+                        let summary: String = serde_json::to_string_pretty(&project_scope)?;
+                        match request_approval(
+                            ApprovalGate::ProjectScope,
+                            &self.attributes.position,
+                            &summary,
+                            cancel,
+                        )
+                        .await?
+                        {
+                            ApprovalDecision::Approve => break,
+                            ApprovalDecision::Edit(flags) => {
+                                for flag in flags.split(',') {
+                                    if !project_scope.toggle_flag(flag) {
+                                        println!("Unknown scope flag '{}'", flag.trim());
+                                    }
+                                }
+                                factsheet.project_scope = Some(project_scope);
+                            }
+                            ApprovalDecision::Regenerate => {
+                                project_scope = self.call_project_scope(factsheet, cancel).await?
+                            }
+                            ApprovalDecision::Abort => {
+                                return Err(Box::new(AgentError::Aborted(
+                                    ApprovalGate::ProjectScope.key().to_string(),
+                                )))
+                            }
+                        }
+                    }
 
                     // Confirm if there are any external URLS
                     if project_scope.is_external_urls_required {
                         self.call_determine_external_urls(
                             factsheet,
                             factsheet.project_description.clone(),
+                            cancel,
                         )
-                        .await;
+                        .await?;
+
+                        // Let the user add or remove URLs before they are tested
+                        loop {
+                            let urls: Vec<String> =
+                                factsheet.external_urls.clone().unwrap_or_default();
+                            match request_approval(
+                                ApprovalGate::ExternalUrls,
+                                &self.attributes.position,
+                                &urls.join("\n"),
+                                cancel,
+                            )
+                            .await?
+                            {
+                                ApprovalDecision::Approve => break,
+                                ApprovalDecision::Edit(edits) => {
+                                    factsheet.external_urls = Some(apply_url_edits(urls, &edits))
+                                }
+                                ApprovalDecision::Regenerate => {
+                                    self.call_determine_external_urls(
+                                        factsheet,
+                                        factsheet.project_description.clone(),
+                                        cancel,
+                                    )
+                                    .await?
+                                }
+                                ApprovalDecision::Abort => {
+                                    return Err(Box::new(AgentError::Aborted(
+                                        ApprovalGate::ExternalUrls.key().to_string(),
+                                    )))
+                                }
+                            }
+                        }
+
                         self.attributes.state = AgentState::UnitTesting;
                     }
                 }
 
                 AgentState::UnitTesting => {
                     let mut exclude_urls: Vec<String> = vec![];
+                    let mut profiles: Vec<UrlProfile> =
+                        factsheet.external_url_profiles.take().unwrap_or_default();
 
-                    let client: Client = Client::builder()
-                        .timeout(Duration::from_secs(5))
-                        .build()
-                        .unwrap();
-
-                    // Defining URLS to check
-                    let urls: &Vec<String> = factsheet
+                    // Defining URLS to check, skipping any that passed in an earlier round
+                    let urls_listed: usize = factsheet
+                        .external_urls
+                        .as_ref()
+                        .expect("No URL object on factsheet")
+                        .len();
+                    let urls: Vec<String> = factsheet
                         .external_urls
                         .as_ref()
-                        .expect("No URL object on factsheet");
+                        .unwrap()
+                        .iter()
+                        .filter(|url| !self.is_url_usable(url))
+                        .cloned()
+                        .collect();
 
-                    // Find faulty URLS
-                    for url in urls {
-                        let endpoint_str: String = format!("Testing URL Endpoint: {}", url);
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            endpoint_str.as_str(),
-                        );
+                    let testing_str: String = format!("Testing {} URL Endpoints...", urls.len());
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        testing_str.as_str(),
+                    );
 
-                        // Perform URL Test
-                        match check_status_code(&client, url).await {
-                            Ok(status_code) => {
-                                if status_code != 200 {
-                                    exclude_urls.push(url.clone())
-                                }
-                            }
-                            Err(e) => println!("Error checking {}: {}", url, e),
+                    // Profile every new URL, excluding any the backend cannot use
+                    let checks: Vec<UrlCheck> = check_urls(&urls, cancel).await?;
+                    let summary_str: String =
+                        format!("URL check results:\n{}", url_check_summary(&checks));
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        summary_str.as_str(),
+                    );
+                    self.url_checks.extend(checks.iter().cloned());
+                    if let Err(e) = save_url_checks(&self.url_checks) {
+                        let err_msg: String = format!("Unable to save the URL checks: {}", e);
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), &err_msg);
+                    }
+                    for check in checks {
+                        if !check.profile.is_usable() {
+                            exclude_urls.push(check.profile.url.clone());
                         }
+                        profiles.push(check.profile);
+                    }
+                    factsheet.external_url_profiles = Some(profiles);
+
+                    // Keep the responses of every usable URL so the backend can be tested against them offline
+                    let listed_urls: &Vec<String> = factsheet.external_urls.as_ref().unwrap();
+                    let snapshots: Vec<UrlSnapshot> = self
+                        .url_checks
+                        .iter()
+                        .filter(|check| check.profile.is_usable())
+                        .filter(|check| listed_urls.contains(&check.profile.url))
+                        .filter_map(|check| check.snapshot.clone())
+                        .collect();
+                    if let Err(e) = save_url_snapshots(&snapshots) {
+                        let err_msg: String = format!("Unable to save the URL responses: {}", e);
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), &err_msg);
                     }
 
                     // Exclude any faulty URLS
@@ -141,6 +348,25 @@ impl SpecialFunctions for AgentSolutionArchitect {
                         factsheet.external_urls = Some(new_urls);
                     }
 
+                    // Ask for replacements if fewer than half of the URLs can be used
+                    let usable_urls: usize = urls_listed - exclude_urls.len();
+                    if usable_urls * 2 < urls_listed
+                        && self.url_replacement_rounds < MAX_URL_REPLACEMENT_ROUNDS
+                    {
+                        self.url_replacement_rounds += 1;
+                        let replace_str: String = format!(
+                            "Only {} of {} URLs can be used, asking for replacements...",
+                            usable_urls, urls_listed
+                        );
+                        PrintCommand::Issue.print_agent_message(
+                            self.attributes.position.as_str(),
+                            replace_str.as_str(),
+                        );
+                        self.rejected_urls.extend(exclude_urls);
+                        self.attributes.state = AgentState::Discovery;
+                        continue;
+                    }
+
                     // Confirm done and set state to finished
                     self.attributes.state = AgentState::Finished;
                 }
@@ -159,6 +385,8 @@ impl SpecialFunctions for AgentSolutionArchitect {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::general::queue_llm_response;
+    use crate::helpers::stub_server::StubServer;
 
     #[tokio::test]
     async fn tests_solution_architect() {
@@ -170,15 +398,128 @@ mod tests {
       external_urls: None,
       backend_code: None,
       api_endpoint_schema: None,
+      ..Default::default()
     };
 
         agent
-            .execute(&mut factsheet)
+            .execute(&mut factsheet, &CancellationToken::new())
             .await
             .expect("Unable to execute Solutions Architect Agent");
-        assert!(factsheet.project_scope != None);
+        assert!(factsheet.project_scope.is_some());
         assert!(factsheet.external_urls.is_some());
 
         dbg!(factsheet);
     }
+
+    #[tokio::test]
+    async fn tests_solution_architect_stops_when_cancelled() {
+        let mut agent: AgentSolutionArchitect = AgentSolutionArchitect::new();
+        let mut factsheet: FactSheet = serde_json::from_str(
+            r#"{
+        "project_description": "build a website that shows forex prices",
+        "project_scope": null,
+        "external_urls": null,
+        "backend_code": null,
+        "api_endpoint_schema": null
+      }"#,
+        )
+        .unwrap();
+
+        let cancel: CancellationToken = CancellationToken::new();
+        cancel.cancel();
+
+        let err: Box<dyn std::error::Error> = agent
+            .execute(&mut factsheet, &cancel)
+            .await
+            .expect_err("Cancelled agent should not run");
+        assert_eq!(
+            err.downcast_ref::<AgentError>(),
+            Some(&AgentError::Cancelled)
+        );
+        assert!(factsheet.project_scope.is_none());
+    }
+
+    #[tokio::test]
+    async fn tests_solution_architect_keeps_usable_urls_when_replacing() {
+        let snapshot = |path: &str| UrlSnapshot {
+            url: format!("https://api.example.com{}", path),
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: r#"{"rates": {"EUR": 0.92}}"#.to_string(),
+        };
+        let stub_server: StubServer = StubServer::start(vec![snapshot("/kept"), snapshot("/new")])
+            .await
+            .expect("Failed to start stub server");
+        let url = |path: &str| {
+            format!(
+                "http://127.0.0.1:{}/api.example.com{}",
+                stub_server.port(),
+                path
+            )
+        };
+
+        let mut agent: AgentSolutionArchitect = AgentSolutionArchitect::new();
+        agent
+            .get_attributes_from_agent_mut()
+            .update_state(AgentState::UnitTesting);
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: "build a website that shows forex prices".to_string(),
+            project_scope: Some(ProjectScope {
+                is_external_urls_required: true,
+                ..Default::default()
+            }),
+            external_urls: Some(vec![url("/kept"), url("/gone"), url("/missing")]),
+            ..Default::default()
+        };
+
+        // Only one of the three URLs can be used, so replacements are asked for
+        queue_llm_response(
+            &serde_json::to_string(&vec![url("/kept"), url("/gone"), url("/new")]).unwrap(),
+        );
+        agent
+            .execute(&mut factsheet, &CancellationToken::new())
+            .await
+            .expect("Unable to execute Solutions Architect Agent");
+
+        assert_eq!(
+            factsheet.external_urls,
+            Some(vec![url("/kept"), url("/new")])
+        );
+        let profiled: Vec<String> = factsheet
+            .external_url_profiles
+            .unwrap()
+            .into_iter()
+            .map(|profile| profile.url)
+            .collect();
+        assert_eq!(profiled, vec![url("/kept"), url("/new")]);
+
+        // The URL that passed the first round is not checked again
+        let kept_checks: usize = agent
+            .url_checks
+            .iter()
+            .filter(|check| check.profile.url == url("/kept"))
+            .count();
+        assert_eq!(kept_checks, 1);
+    }
+
+    #[test]
+    fn tests_apply_url_edits() {
+        let urls: Vec<String> = vec![
+            "https://api.one.com/prices".to_string(),
+            "https://api.two.com/rates".to_string(),
+        ];
+
+        let edited: Vec<String> = apply_url_edits(
+            urls,
+            "-https://api.one.com/prices +https://api.three.com/fx, +https://api.two.com/rates",
+        );
+
+        assert_eq!(
+            edited,
+            vec![
+                "https://api.two.com/rates".to_string(),
+                "https://api.three.com/fx".to_string()
+            ]
+        );
+    }
 }
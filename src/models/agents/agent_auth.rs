@@ -0,0 +1,282 @@
+use crate::ai_functions::aifunc_auth::print_code_with_auth;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{ai_task_request_decoded, save_backend_files};
+use crate::helpers::web_server::run_cargo_with_env;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, FactSheet, ProjectFile, SpecialFunctions};
+
+use async_trait::async_trait;
+use reqwest::Client;
+use std::process::Output;
+use tokio_util::sync::CancellationToken;
+
+/// Route that creates a user
+pub const REGISTER_ROUTE: &str = "/register";
+
+/// Route that returns a session token for a valid username and password
+pub const LOGIN_ROUTE: &str = "/login";
+
+/// Route that ends a session
+pub const LOGOUT_ROUTE: &str = "/logout";
+
+/// Username of the user created to test protected routes
+const TEST_USERNAME: &str = "endpoint_tester";
+
+/// Password of the user created to test protected routes
+const TEST_PASSWORD: &str = "endpoint-tester-password";
+
+/// Maximum number of times the auth code is regenerated when parts of it are missing
+const MAX_AUTH_FIXES: u8 = 2;
+
+/// Represents an Auth Engineer agent
+#[derive(Debug)]
+pub struct AgentAuthEngineer {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+    /// Parts of the auth contract missing from the last generated code
+    missing_auth: Vec<String>,
+    /// Total number of times the auth code has been regenerated
+    fix_count: u8,
+}
+
+/// Implementation for an Auth Engineer agent
+impl AgentAuthEngineer {
+    /// Create a new Auth Engineer agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Adds registration, login, logout and protected routes to the backend"
+                .to_string(),
+            position: "Auth Engineer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self {
+            attributes,
+            missing_auth: vec![],
+            fix_count: 0,
+        }
+    }
+
+    /// Add authentication to the backend code
+    async fn call_code_with_auth(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n BACKEND_FILES: {} \n",
+            factsheet.project_description,
+            serde_json::to_string(&factsheet.backend_files)?
+        );
+        if !self.missing_auth.is_empty() {
+            msg_context.push_str(&format!("MISSING_AUTH: {:?} \n", self.missing_auth));
+        }
+
+        let changed_files: Vec<ProjectFile> = ai_task_request_decoded::<Vec<ProjectFile>>(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_code_with_auth),
+            print_code_with_auth,
+            cancel,
+        )
+        .await?;
+
+        save_backend_files(factsheet, changed_files).map_err(AgentError::Failed)?;
+        Ok(())
+    }
+}
+
+/// Check the backend code for every part of the auth contract
+/// Returns a description of every part that is missing
+pub fn check_auth_code(code: &str) -> Vec<String> {
+    let mut missing: Vec<String> = vec![];
+
+    for route in [REGISTER_ROUTE, LOGIN_ROUTE, LOGOUT_ROUTE] {
+        if !code.contains(&format!("\"{}\"", route)) {
+            missing.push(format!("There is no POST {} route", route));
+        }
+    }
+    if !code.contains("Argon2") {
+        missing.push("Passwords are not hashed with argon2".to_string());
+    }
+    if !code.contains("SaltString") {
+        missing.push("Password hashes are not salted with a generated SaltString".to_string());
+    }
+    if !code.contains("Authorization") {
+        missing.push("No route checks the Authorization header for a session token".to_string());
+    }
+
+    missing
+}
+
+/// Read the session token from a login response
+fn extract_token(body: &serde_json::Value) -> Option<String> {
+    ["token", "access_token"]
+        .iter()
+        .find_map(|key| body.get(key)?.as_str())
+        .map(|token| token.to_string())
+}
+
+/// Register a test user (if it does not exist yet) and log in as it
+/// Returns the session token to send to protected routes
+/// # Arguments
+///
+/// * `client` - The client making the requests
+/// * `base_url` - The address of the running web server (i.e. http://localhost:8080)
+pub async fn log_in_test_user(client: &Client, base_url: &str) -> Result<String, String> {
+    let credentials: serde_json::Value = serde_json::json!({
+        "username": TEST_USERNAME,
+        "password": TEST_PASSWORD,
+    });
+
+    // Registering fails if the user already exists, which is fine as long as the login works
+    let _ = client
+        .post(format!("{}{}", base_url, REGISTER_ROUTE))
+        .json(&credentials)
+        .send()
+        .await;
+
+    let response: reqwest::Response = client
+        .post(format!("{}{}", base_url, LOGIN_ROUTE))
+        .json(&credentials)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Login failed with status {}", response.status()));
+    }
+
+    let body: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    extract_token(&body).ok_or_else(|| "Login response did not contain a token".to_string())
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentAuthEngineer {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    if factsheet.backend_code.is_none() {
+                        return Err(Box::new(AgentError::Failed(
+                            "There is no backend code to add authentication to".to_string(),
+                        )));
+                    }
+
+                    // The template only has the crates it needs, so add the ones used for hashing
+                    let add_output: Output = run_cargo_with_env(
+                        &[
+                            "add",
+                            "argon2@0.5",
+                            "password-hash@0.5",
+                            "--features",
+                            "password-hash/getrandom",
+                        ],
+                        &[],
+                        None,
+                        &[],
+                        cancel,
+                    )
+                    .await?;
+                    if !add_output.status.success() {
+                        return Err(Box::new(AgentError::Failed(format!(
+                            "Failed to add password hashing crates: {}",
+                            String::from_utf8_lossy(&add_output.stderr)
+                        ))));
+                    }
+
+                    self.missing_auth.clear();
+                    self.fix_count = 0;
+                    self.attributes.state = AgentState::Working;
+                }
+
+                AgentState::Working => {
+                    self.call_code_with_auth(factsheet, cancel).await?;
+                    self.attributes.state = AgentState::UnitTesting;
+                }
+
+                AgentState::UnitTesting => {
+                    let backend_code: &str = factsheet.backend_code.as_deref().unwrap_or_default();
+                    self.missing_auth = check_auth_code(backend_code);
+
+                    if self.missing_auth.is_empty() {
+                        PrintCommand::UnitTest.print_agent_message(
+                            self.attributes.position.as_str(),
+                            "Auth Unit Testing: Registration, login, logout and protected routes added",
+                        );
+                        self.attributes.state = AgentState::Finished;
+                        continue;
+                    }
+
+                    for missing in &self.missing_auth {
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), missing);
+                    }
+
+                    self.fix_count += 1;
+                    if self.fix_count > MAX_AUTH_FIXES {
+                        return Err(Box::new(AgentError::Failed(
+                            "Authentication is still incomplete".to_string(),
+                        )));
+                    }
+                    self.attributes.state = AgentState::Working;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_check_auth_code() {
+        let code: &str = r#"
+            let salt = SaltString::generate(&mut OsRng);
+            let hash = Argon2::default().hash_password(password.as_bytes(), &salt);
+            .route("/register", web::post().to(register))
+            .route("/login", web::post().to(login))
+        "#;
+
+        assert_eq!(
+            check_auth_code(code),
+            vec![
+                "There is no POST /logout route".to_string(),
+                "No route checks the Authorization header for a session token".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_extract_token() {
+        assert_eq!(
+            extract_token(&serde_json::json!({ "token": "abc" })),
+            Some("abc".to_string())
+        );
+        assert_eq!(
+            extract_token(&serde_json::json!({ "access_token": "xyz" })),
+            Some("xyz".to_string())
+        );
+        assert_eq!(extract_token(&serde_json::json!({ "user": "bob" })), None);
+    }
+}
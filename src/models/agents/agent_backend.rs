@@ -1,23 +1,78 @@
 use crate::ai_functions::aifunc_backend::{
-    print_backend_webserver_code, print_fixed_code, print_improved_webserver_code,
-    print_rest_api_endpoints,
+    print_backend_webserver_code, print_code_addressing_review, print_code_fix_edits,
+    print_fixed_code, print_improved_webserver_code, print_rest_api_endpoints,
+};
+use crate::helpers::endpoint_probe::{
+    extract_id, fill_route, is_dynamic, plan_lifecycle, sample_body, send_probe,
+    unsupported_endpoints, validate_response, ContractMismatch, LifecyclePhase,
 };
 use crate::helpers::general::{
-    check_status_code, read_code_template_contents, read_exec_main_contents, save_api_endpoints,
-    save_backend_code,
+    files_named_in, read_code_template_contents, reload_backend_files, save_api_endpoints,
+    save_backend_files, save_run_artifact,
 };
 
-use crate::helpers::command_line::{confirm_safe_code, PrintCommand};
-use crate::helpers::general::ai_task_request;
+use crate::helpers::command_line::{
+    confirm_safe_code, request_approval, ApprovalDecision, ApprovalGate, PrintCommand,
+};
+use crate::helpers::diagnostics::{
+    apply_machine_fixes, compact_errors, parse_cargo_diagnostics, Diagnostic,
+};
+use crate::helpers::general::{ai_task_request, ai_task_request_decoded};
+use crate::helpers::patch::{apply_edits, CodeEdit, PatchRecord};
+use crate::helpers::scope_features::{active_features, describe_features, ScopeFeature};
+use crate::helpers::stub_server::{external_base_urls, load_url_snapshots, StubServer};
+use crate::helpers::web_server::{
+    allocate_port, find_runtime_errors, run_cargo, run_cargo_with_env, server_base_url,
+    server_ready_timeout, spawn_web_server, wait_for_server, ServerLog,
+};
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, RouteObject, SpecialFunctions};
+use crate::models::agents::agent_auth::{
+    log_in_test_user, AgentAuthEngineer, LOGIN_ROUTE, LOGOUT_ROUTE, REGISTER_ROUTE,
+};
+use crate::models::agents::agent_database::check_structs_against_model;
+use crate::models::agents::agent_reviewer::AgentCodeReviewer;
+use crate::models::agents::agent_security::{
+    block_severity, format_findings, AgentSecurityAuditor,
+};
+use crate::models::agents::agent_traits::{
+    AgentError, Escalation, FactSheet, ProjectFile, ReviewComment, RouteObject, SecurityFinding,
+    SpecialFunctions, UrlProfile,
+};
 
 use async_trait::async_trait;
 use reqwest::Client;
-use std::env;
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of times compiler suggestions are applied before the errors go to the LLM
+const MAX_MACHINE_FIX_ROUNDS: u8 = 3;
+
+/// Time given to the server to print what went wrong after a failed request
+const SERVER_LOG_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// Stages of backend unit testing, run in order once the code passes the safety checks
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum UnitTestStage {
+    Build,
+    RouteExtraction,
+    Launch,
+    Probe,
+    Teardown,
+}
+
+/// Represents the result of a single unit testing stage
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StageReport {
+    /// The stage that was run
+    pub stage: UnitTestStage,
+    /// Flag to determine if the stage passed
+    pub is_passed: bool,
+    /// What went wrong, if the stage failed
+    pub failures: Vec<String>,
+}
 
 /// Represents a backend developer agent
 #[derive(Debug)]
@@ -28,6 +83,12 @@ pub struct AgentBackendDeveloper {
     bug_errors: Option<String>,
     /// Total number of bug errors stored
     bug_count: u8,
+    /// Requirements the manager found missing that still need to be built
+    follow_up_tasks: Vec<String>,
+    /// Total number of fix patches made during the run (never reset, so no patch file is overwritten)
+    patch_count: u8,
+    /// Results of the stages run in the current unit testing pass
+    stage_reports: Vec<StageReport>,
 }
 
 /// Implementation for a Backend Developer Agent
@@ -45,91 +106,539 @@ impl AgentBackendDeveloper {
             attributes,
             bug_errors: None,
             bug_count: 0,
+            follow_up_tasks: vec![],
+            patch_count: 0,
+            stage_reports: vec![],
         }
     }
 
     /// Call the initial backend code provided as instructions
     /// Think of this as code written as a Junior Developer
-    async fn call_initial_backend_code(&mut self, factsheet: &mut FactSheet) {
+    async fn call_initial_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let code_template_str: String = read_code_template_contents();
 
         // Concatenate instructions
-        let msg_context: String = format!(
+        let mut msg_context: String = format!(
             "CODE TEMPLATE: {} \n PROJECT_DESCRIPTION: {} \n",
             code_template_str, factsheet.project_description
         );
 
+        // Build what the user asked for where the description is ambiguous
+        if let Some(requirements) = &factsheet.requirements {
+            msg_context.push_str(&format!(
+                "REQUIREMENTS: {} \n",
+                serde_json::to_string(requirements).unwrap_or_default()
+            ));
+        }
+
+        // Generate structs that match the designed data model
+        if let Some(data_model) = &factsheet.data_model {
+            msg_context.push_str(&format!(
+                "DATA_MODEL (write one struct per entity with exactly these fields and types): {} \n",
+                serde_json::to_string(data_model).unwrap_or_default()
+            ));
+        }
+
+        // Read external hosts from environment variables so the tests can point them at the stub server
+        msg_context.push_str(&external_urls_context(factsheet));
+        msg_context.push_str(&scope_features_context(factsheet));
+
         // Generate a response from the AI model
-        let ai_response: String = ai_task_request(
+        let backend_files: Vec<ProjectFile> = ai_task_request_decoded::<Vec<ProjectFile>>(
             msg_context,
             &self.attributes.position,
             get_function_string!(print_backend_webserver_code),
             print_backend_webserver_code,
+            cancel,
         )
-        .await;
+        .await?;
 
-        // Save the backend code to the factsheet
-        save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        // Save the backend files to the factsheet, starting again from the template
+        factsheet.backend_files = None;
+        save_backend_files(factsheet, backend_files).map_err(AgentError::Failed)?;
+        Ok(())
     }
 
     /// Call the improved backend code provided from the factsheet
     /// Think of this as code improved by a Senior Developer
-    async fn call_improved_backend_code(&mut self, factsheet: &mut FactSheet) {
-        let msg_context: String = format!(
-            "CODE TEMPLATE: {:?} \n PROJECT_DESCRIPTION: {:?} \n",
-            factsheet.backend_code, factsheet
+    async fn call_improved_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut msg_context: String = format!(
+            "BACKEND_FILES: {} \n PROJECT_DESCRIPTION: {:?} \n",
+            serde_json::to_string(&factsheet.backend_files)?,
+            factsheet
         );
 
-        let ai_response: String = ai_task_request(
+        msg_context.push_str(&external_urls_context(factsheet));
+        msg_context.push_str(&scope_features_context(factsheet));
+
+        // Include any requirements the manager found missing in a review
+        if !self.follow_up_tasks.is_empty() {
+            let follow_up_tasks: Vec<String> = std::mem::take(&mut self.follow_up_tasks);
+            msg_context.push_str(&format!(
+                "MISSING_REQUIREMENTS_TO_BUILD: {:?} \n",
+                follow_up_tasks
+            ));
+        }
+
+        let changed_files: Vec<ProjectFile> = ai_task_request_decoded::<Vec<ProjectFile>>(
             msg_context,
             &self.attributes.position,
             get_function_string!(print_improved_webserver_code),
             print_improved_webserver_code,
+            cancel,
         )
-        .await;
+        .await?;
 
-        save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        save_backend_files(factsheet, changed_files).map_err(AgentError::Failed)?;
+        Ok(())
     }
 
-    /// Fix any backend code that has been flagged to have bugs
-    async fn call_fix_code_bugs(&mut self, factsheet: &mut FactSheet) {
+    /// Have a separate Code Reviewer review the code, then address its comments before building
+    async fn call_review_and_address_comments(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut reviewer: AgentCodeReviewer = AgentCodeReviewer::new();
+
+        // A failed review should not stop the build, but cancellation should
+        if let Err(e) = reviewer.execute(factsheet, cancel).await {
+            if let Some(AgentError::Cancelled) = e.downcast_ref::<AgentError>() {
+                return Err(e);
+            }
+            let err_msg: String = format!("Code review failed, continuing without it: {}", e);
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, &err_msg);
+            return Ok(());
+        }
+
+        let review_comments: &Vec<ReviewComment> = match &factsheet.review_comments {
+            Some(review_comments) if !review_comments.is_empty() => review_comments,
+            _ => return Ok(()),
+        };
+
         let msg_context: String = format!(
-            "BROKEN_CODE: {:?} \n ERROR_BUGS: {:?} \n
-      THIS FUNCTION ONLY OUTPUTS CODE. JUST OUTPUT THE CODE.",
-            factsheet.backend_code, self.bug_errors
+            "BACKEND_FILES: {} \n REVIEW_COMMENTS: {} \n",
+            serde_json::to_string(&factsheet.backend_files)?,
+            serde_json::to_string(review_comments)?
         );
 
-        let ai_response: String = ai_task_request(
+        let changed_files: Vec<ProjectFile> = ai_task_request_decoded::<Vec<ProjectFile>>(
             msg_context,
             &self.attributes.position,
-            get_function_string!(print_fixed_code),
-            print_fixed_code,
+            get_function_string!(print_code_addressing_review),
+            print_code_addressing_review,
+            cancel,
+        )
+        .await?;
+
+        save_backend_files(factsheet, changed_files).map_err(AgentError::Failed)?;
+        Ok(())
+    }
+
+    /// Add the crates needed by the features in the project scope (i.e. rusqlite for SQLite)
+    async fn call_add_feature_crates(
+        &self,
+        factsheet: &FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
+        let features: Vec<ScopeFeature> = factsheet
+            .project_scope
+            .map(|scope| active_features(&scope))
+            .unwrap_or_default();
+
+        for feature in features {
+            for crate_args in feature.crates {
+                let mut args: Vec<&str> = vec!["add"];
+                args.extend(crate_args.split_whitespace());
+
+                let add_output: std::process::Output =
+                    run_cargo_with_env(&args, &[], None, &[], cancel).await?;
+                if !add_output.status.success() {
+                    return Err(AgentError::Failed(format!(
+                        "Failed to add the {} crates: {}",
+                        feature.name,
+                        String::from_utf8_lossy(&add_output.stderr)
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add registration, login, logout and protected routes when the project needs users to log in
+    async fn call_add_authentication(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let is_auth_required: bool = factsheet
+            .project_scope
+            .is_some_and(|scope| scope.is_auth_required());
+        if !is_auth_required {
+            return Ok(());
+        }
+
+        // Missing auth is caught by the requirement review, so only cancellation stops the build
+        // The failure is recorded so the probe does not log in to a server without auth routes
+        let mut auth_engineer: AgentAuthEngineer = AgentAuthEngineer::new();
+        factsheet.is_auth_incomplete = false;
+        if let Err(e) = auth_engineer.execute(factsheet, cancel).await {
+            if let Some(AgentError::Cancelled) = e.downcast_ref::<AgentError>() {
+                return Err(e);
+            }
+            factsheet.is_auth_incomplete = true;
+            let err_msg: String = format!("Adding authentication failed, continuing: {}", e);
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, &err_msg);
+        }
+        Ok(())
+    }
+
+    /// Store bugs so they are fixed in the next iteration
+    /// Returns an error once too many bugs have been found
+    fn record_bugs(&mut self, bugs: String) -> Result<(), AgentError> {
+        self.bug_count += 1;
+        self.bug_errors = Some(bugs);
+
+        // Check for bugs and exit if too many bugs occur
+        if self.bug_count > 2 {
+            PrintCommand::Issue.print_agent_message(
+                self.attributes.position.as_str(),
+                "Backend Code Unit Testing: Too many bugs found in code",
+            );
+            return Err(AgentError::Failed(
+                "Too many bugs found in backend code".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Report the result of a unit testing stage and save the results of the pass so far
+    fn report_stage(&mut self, stage: UnitTestStage, failures: Vec<String>) {
+        let is_passed: bool = failures.is_empty();
+        let stage_msg: String = match is_passed {
+            true => format!("Backend Code Unit Testing: {:?} stage passed", stage),
+            false => format!("Backend Code Unit Testing: {:?} stage failed", stage),
+        };
+        PrintCommand::UnitTest.print_agent_message(&self.attributes.position, &stage_msg);
+        for failure in &failures {
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, failure);
+        }
+
+        self.stage_reports.push(StageReport {
+            stage,
+            is_passed,
+            failures,
+        });
+        if let Err(e) = save_run_artifact("backend_unit_tests.json", &self.stage_reports) {
+            let err_msg: String = format!("Unable to save backend_unit_tests.json: {}", e);
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, &err_msg);
+        }
+    }
+
+    /// Call every endpoint, creating, reading, updating and deleting an item of each resource in turn
+    /// Dynamic routes are filled with the id of the item created earlier in the lifecycle
+    /// Returns a description of every request that failed, and every response that did not match the schema
+    async fn call_probe_endpoints(
+        &self,
+        client: &Client,
+        base_url: &str,
+        endpoints: &[RouteObject],
+        token: Option<&str>,
+        server_log: &ServerLog,
+        cancel: &CancellationToken,
+    ) -> Result<(Vec<String>, Vec<ContractMismatch>), AgentError> {
+        // A fresh id per run, so items left by earlier runs do not clash
+        let sample_id: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |elapsed| elapsed.as_secs() % 1_000_000 + 1);
+        let mut created_ids: HashMap<String, String> = HashMap::new();
+        let mut failures: Vec<String> = vec![];
+        let mut contract_report: Vec<ContractMismatch> = vec![];
+
+        for endpoint in unsupported_endpoints(endpoints) {
+            let skip_msg: String = format!(
+                "Skipping endpoint '{} {}' as the probe does not support its method",
+                endpoint.method, endpoint.route
+            );
+            PrintCommand::Issue.print_agent_message(self.attributes.position.as_str(), &skip_msg);
+        }
+
+        for step in plan_lifecycle(endpoints) {
+            if cancel.is_cancelled() {
+                return Err(AgentError::Cancelled);
+            }
+
+            let method: String = step.method.to_string();
+            let route: String = match is_dynamic(&step.endpoint) {
+                true => match created_ids.get(&step.resource) {
+                    Some(id) => fill_route(&step.endpoint.route, id),
+                    None => {
+                        let skip_msg: String = format!(
+                            "Skipping endpoint '{} {}' as no item was created to call it with",
+                            method, step.endpoint.route
+                        );
+                        PrintCommand::UnitTest
+                            .print_agent_message(self.attributes.position.as_str(), &skip_msg);
+                        continue;
+                    }
+                },
+                false => step.endpoint.route.clone(),
+            };
+            let body: Option<serde_json::Value> =
+                sample_body(&step.endpoint.request_body, step.phase, sample_id);
+
+            // An update without a known body shape would only be rejected, which says nothing about the server
+            if step.phase == LifecyclePhase::Update && body.is_none() {
+                let skip_msg: String = format!(
+                    "Skipping endpoint '{} {}' as its request body is unknown",
+                    method, step.endpoint.route
+                );
+                PrintCommand::UnitTest
+                    .print_agent_message(self.attributes.position.as_str(), &skip_msg);
+                continue;
+            }
+
+            let testing_msg: String = format!("Testing endpoint '{} {}'...", method, route);
+            PrintCommand::UnitTest
+                .print_agent_message(self.attributes.position.as_str(), &testing_msg);
+
+            let url: String = format!("{}{}", base_url, route);
+            let endpoint_token: Option<&str> = match step.endpoint.is_protected {
+                true => token,
+                false => None,
+            };
+            let log_mark: usize = server_log.mark();
+            let probe_result: Result<(u16, serde_json::Value), reqwest::Error> =
+                send_probe(client, &step.method, &url, body.as_ref(), endpoint_token).await;
+            if !matches!(probe_result, Ok((status_code, _)) if status_code < 500) {
+                // Give the server a moment to finish printing what went wrong
+                time::sleep(SERVER_LOG_SETTLE_TIME).await;
+            }
+
+            // Tie anything the server logged during the request to that request
+            let runtime_errors: Vec<String> =
+                find_runtime_errors(&server_log.lines_since(log_mark));
+            let with_server_output = |failure: String| match runtime_errors.is_empty() {
+                true => failure,
+                false => format!(
+                    "{} \n SERVER_OUTPUT: {}",
+                    failure,
+                    runtime_errors.join("\n")
+                ),
+            };
+
+            match probe_result {
+                Ok((status_code, response)) if (200..300).contains(&status_code) => {
+                    if !runtime_errors.is_empty() {
+                        failures.push(with_server_output(format!(
+                            "{} {} succeeded but the server logged errors",
+                            method, route
+                        )));
+                    }
+
+                    let mismatches: Vec<String> =
+                        validate_response(&step.endpoint.response, &response);
+                    if !mismatches.is_empty() {
+                        contract_report.push(ContractMismatch {
+                            method: method.clone(),
+                            route: route.clone(),
+                            mismatches,
+                        });
+                    }
+
+                    // Servers that echo nothing back keep the id that was sent
+                    if step.phase == LifecyclePhase::Create {
+                        let id: Option<String> =
+                            extract_id(&response).or_else(|| body.as_ref().and_then(extract_id));
+                        if let Some(id) = id {
+                            created_ids.insert(step.resource.clone(), id);
+                        }
+                    }
+                }
+                Ok((status_code, _)) => failures.push(with_server_output(format!(
+                    "{} {} returned status {}",
+                    method, route, status_code
+                ))),
+                Err(e) => {
+                    // The server is unreachable so there is nothing left to test against
+                    failures.push(with_server_output(format!(
+                        "{} {} could not be called: {}",
+                        method, route, e
+                    )));
+                    break;
+                }
+            }
+        }
+
+        Ok((failures, contract_report))
+    }
+
+    /// Fix any backend code that has been flagged to have bugs
+    /// Only the files named in the errors are sent, so the rest of the project is left untouched
+    async fn call_fix_code_bugs(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let bugs: String = self.bug_errors.clone().unwrap_or_default();
+        let backend_files: &[ProjectFile] = factsheet.backend_files.as_deref().unwrap_or_default();
+        let broken_files: Vec<ProjectFile> = files_named_in(&bugs, backend_files);
+        let other_files: Vec<&str> = backend_files
+            .iter()
+            .filter(|file| !broken_files.contains(file))
+            .map(|file| file.path.as_str())
+            .collect();
+
+        let msg_context: String = format!(
+            "BROKEN_FILES: {} \n OTHER_FILES: {:?} \n ERROR_BUGS: {:?} \n",
+            serde_json::to_string(&broken_files)?,
+            other_files,
+            bugs
+        );
+
+        // Ask for small edits first, as regenerating whole files often breaks working code
+        let edits: Result<Vec<CodeEdit>, AgentError> = ai_task_request_decoded::<Vec<CodeEdit>>(
+            msg_context.clone(),
+            &self.attributes.position,
+            get_function_string!(print_code_fix_edits),
+            print_code_fix_edits,
+            cancel,
         )
         .await;
+        if let Err(AgentError::Cancelled) = edits {
+            return Err(Box::new(AgentError::Cancelled));
+        }
+        let patched_files: Result<Vec<ProjectFile>, String> = match &edits {
+            Ok(edits) => apply_edits(&broken_files, edits),
+            Err(e) => Err(format!("Unable to read edits: {}", e)),
+        };
+
+        // Keep every patch so the user can review what changed between iterations
+        self.patch_count += 1;
+        let patch_record: PatchRecord = PatchRecord {
+            iteration: self.patch_count,
+            edits: edits.unwrap_or_default(),
+            fallback_reason: patched_files.as_ref().err().cloned(),
+        };
+        let patch_file: String = format!("fix_patch_{}.json", self.patch_count);
+        if let Err(e) = save_run_artifact(&patch_file, &patch_record) {
+            let err_msg: String = format!("Unable to save {}: {}", patch_file, e);
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, &err_msg);
+        }
+
+        let fixed_files: Vec<ProjectFile> = match patched_files {
+            Ok(patched_files) => patched_files,
+            Err(reason) => {
+                let err_msg: String =
+                    format!("Patch failed to apply, regenerating the files: {}", reason);
+                PrintCommand::Issue.print_agent_message(&self.attributes.position, &err_msg);
+
+                ai_task_request_decoded::<Vec<ProjectFile>>(
+                    msg_context,
+                    &self.attributes.position,
+                    get_function_string!(print_fixed_code),
+                    print_fixed_code,
+                    cancel,
+                )
+                .await?
+            }
+        };
 
-        save_backend_code(&ai_response);
-        factsheet.backend_code = Some(ai_response);
+        save_backend_files(factsheet, fixed_files).map_err(AgentError::Failed)?;
+        Ok(())
     }
 
     /// Extract the REST API endpoints
-    async fn call_extract_rest_api_endpoints(&self) -> String {
-        let backend_code: String = read_exec_main_contents();
+    async fn call_extract_rest_api_endpoints(
+        &self,
+        factsheet: &FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<String, AgentError> {
+        let backend_code: &str = factsheet.backend_code.as_deref().unwrap_or_default();
 
         // Structure message context
         let msg_context: String = format!("CODE_INPUT: {}", backend_code);
 
-        let ai_response: String = ai_task_request(
+        ai_task_request(
             msg_context,
             &self.attributes.position,
             get_function_string!(print_rest_api_endpoints),
             print_rest_api_endpoints,
+            cancel,
         )
-        .await;
+        .await
+    }
+}
 
-        ai_response
+/// Split the endpoints into the ones to probe and the protected ones skipped because auth is incomplete
+/// The auth routes are called while logging in, and logging out would end the session
+fn probe_targets(
+    api_endpoints: &[RouteObject],
+    is_auth_incomplete: bool,
+) -> (Vec<RouteObject>, Vec<&RouteObject>) {
+    let (skipped, check): (Vec<&RouteObject>, Vec<&RouteObject>) = api_endpoints
+        .iter()
+        .filter(|&route_object| {
+            ![REGISTER_ROUTE, LOGIN_ROUTE, LOGOUT_ROUTE].contains(&route_object.route.as_str())
+        })
+        .partition(|&route_object| is_auth_incomplete && route_object.is_protected);
+    (check.into_iter().cloned().collect(), skipped)
+}
+
+/// Describe the external URLs with the environment variable each base URL is read from
+/// Empty if the project does not call any external URLs
+fn external_urls_context(factsheet: &FactSheet) -> String {
+    let urls: Vec<String> = factsheet.external_urls.clone().unwrap_or_default();
+    if urls.is_empty() {
+        return String::new();
     }
+
+    let base_urls: HashMap<String, String> = external_base_urls(&urls).into_iter().collect();
+    let mut context: String = format!(
+        "EXTERNAL_URLS: {:?} \n EXTERNAL_BASE_URL_ENV_VARS: {} \n",
+        urls,
+        serde_json::to_string(&base_urls).unwrap_or_default()
+    );
+
+    // Real payload shapes, so the structs decoding the responses match them
+    let profiles: Vec<&UrlProfile> = factsheet
+        .external_url_profiles
+        .iter()
+        .flatten()
+        .filter(|profile| profile.is_usable() && urls.contains(&profile.url))
+        .collect();
+    if !profiles.is_empty() {
+        context.push_str(&format!(
+            "EXTERNAL_URL_PROFILES: {} \n",
+            serde_json::to_string(&profiles).unwrap_or_default()
+        ));
+    }
+    context
+}
+
+/// Describe what the backend has to build for the features in the project scope
+/// Empty if the scope does not turn on any feature
+fn scope_features_context(factsheet: &FactSheet) -> String {
+    let features: Vec<ScopeFeature> = factsheet
+        .project_scope
+        .map(|scope| active_features(&scope))
+        .unwrap_or_default();
+    if features.is_empty() {
+        return String::new();
+    }
+
+    format!(
+        "SCOPE_FEATURES: {} \n",
+        describe_features(&features, |feature| feature.backend_instruction)
+    )
 }
 
 #[async_trait]
@@ -138,189 +647,400 @@ impl SpecialFunctions for AgentBackendDeveloper {
         &self.attributes
     }
 
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    /// Fix the existing code using the problems found by a later agent (i.e. failing integration tests)
+    fn receive_escalation(&mut self, escalation: &Escalation) {
+        self.bug_count = 1;
+        self.bug_errors = Some(escalation.reason.clone());
+        self.attributes.state = AgentState::Working;
+    }
+
+    /// Improve the existing code with the missing requirements rather than starting again
+    fn receive_follow_up(&mut self, tasks: &[String]) {
+        self.follow_up_tasks = tasks.to_vec();
+        self.bug_count = 0;
+        self.bug_errors = None;
+        self.attributes.state = AgentState::Working;
+    }
+
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
     ) -> Result<(), Box<dyn std::error::Error>> {
         while self.attributes.state != AgentState::Finished {
             match &self.attributes.state {
                 AgentState::Discovery => {
-                    self.call_initial_backend_code(factsheet).await;
+                    // Ask the Solutions Architect for URLs if the project needs them but none survived
+                    let is_external_urls_required: bool = factsheet
+                        .project_scope
+                        .is_some_and(|scope| scope.is_external_urls_required);
+                    let has_external_urls: bool = factsheet
+                        .external_urls
+                        .as_ref()
+                        .is_some_and(|urls| !urls.is_empty());
+                    if is_external_urls_required && !has_external_urls {
+                        let rejected_urls: Vec<String> = factsheet
+                            .external_url_profiles
+                            .iter()
+                            .flatten()
+                            .filter(|profile| !profile.is_usable())
+                            .map(|profile| profile.url.clone())
+                            .collect();
+                        return Err(Box::new(AgentError::Escalation(Escalation {
+                            target_position: "Solutions Architect".to_string(),
+                            reason: "Project requires external URLs but none are usable"
+                                .to_string(),
+                            rejected_urls,
+                        })));
+                    }
+
+                    self.bug_count = 0;
+                    self.bug_errors = None;
+                    self.call_add_feature_crates(factsheet, cancel).await?;
+                    self.call_initial_backend_code(factsheet, cancel).await?;
                     self.attributes.state = AgentState::Working;
                     continue;
                 }
                 AgentState::Working => {
                     if self.bug_count == 0 {
-                        self.call_improved_backend_code(factsheet).await;
+                        self.call_improved_backend_code(factsheet, cancel).await?;
+                        self.call_add_authentication(factsheet, cancel).await?;
+                        self.call_review_and_address_comments(factsheet, cancel)
+                            .await?;
                     } else {
-                        self.call_fix_code_bugs(factsheet).await;
+                        // Let the user review the bugs before another fix is attempted
+                        let bugs: String = self.bug_errors.clone().unwrap_or_default();
+                        match request_approval(
+                            ApprovalGate::FixIteration,
+                            &self.attributes.position,
+                            &bugs,
+                            cancel,
+                        )
+                        .await?
+                        {
+                            ApprovalDecision::Approve => {}
+                            ApprovalDecision::Edit(guidance) => {
+                                self.bug_errors =
+                                    Some(format!("{} \n DEVELOPER_GUIDANCE: {}", bugs, guidance));
+                            }
+                            ApprovalDecision::Regenerate => {
+                                // Start again from the code template
+                                self.bug_count = 0;
+                                self.bug_errors = None;
+                                self.attributes.state = AgentState::Discovery;
+                                continue;
+                            }
+                            ApprovalDecision::Abort => {
+                                return Err(Box::new(AgentError::Aborted(
+                                    ApprovalGate::FixIteration.key().to_string(),
+                                )))
+                            }
+                        }
+                        self.call_fix_code_bugs(factsheet, cancel).await?;
                     }
                     self.attributes.state = AgentState::UnitTesting;
                     continue;
                 }
                 AgentState::UnitTesting => {
+                    self.stage_reports.clear();
+
+                    // Check the generated structs match the data model before building anything
+                    if let (Some(data_model), Some(backend_code)) =
+                        (&factsheet.data_model, &factsheet.backend_code)
+                    {
+                        let mismatches: Vec<String> =
+                            check_structs_against_model(backend_code, data_model);
+                        if !mismatches.is_empty() {
+                            for mismatch in &mismatches {
+                                PrintCommand::Issue.print_agent_message(
+                                    self.attributes.position.as_str(),
+                                    mismatch,
+                                );
+                            }
+                            self.record_bugs(format!("DATA_MODEL_MISMATCHES: {:?}", mismatches))?;
+                            self.attributes.state = AgentState::Working;
+                            continue;
+                        }
+                    }
+
                     // Guard:: ENSURE AI SAFETY
                     PrintCommand::UnitTest.print_agent_message(
                         self.attributes.position.as_str(),
                         "Backend Code Unit Testing: Requesting user input",
                     );
 
-                    let is_safe_code: bool = confirm_safe_code();
+                    // Scan the code before anything is built, blocking the build on serious findings
+                    let mut security_auditor: AgentSecurityAuditor = AgentSecurityAuditor::new();
+                    security_auditor.execute(factsheet, cancel).await?;
+                    let findings: &[SecurityFinding] =
+                        factsheet.security_findings.as_deref().unwrap_or_default();
+                    if let Some(threshold) = block_severity() {
+                        let blocking: Vec<SecurityFinding> = findings
+                            .iter()
+                            .filter(|finding| finding.severity >= threshold)
+                            .cloned()
+                            .collect();
+                        if !blocking.is_empty() {
+                            PrintCommand::Issue.print_agent_message(
+                                self.attributes.position.as_str(),
+                                "Backend Code Unit Testing: Security findings block the build",
+                            );
+                            self.record_bugs(format!(
+                                "SECURITY_FINDINGS: {}",
+                                format_findings(&blocking)
+                            ))?;
+                            self.attributes.state = AgentState::Working;
+                            continue;
+                        }
+                    }
 
-                    if !is_safe_code {
-                        panic!("Better go work on some AI alignment instead...")
+                    match confirm_safe_code(
+                        &self.attributes.position,
+                        &format_findings(findings),
+                        cancel,
+                    )
+                    .await?
+                    {
+                        ApprovalDecision::Approve => {}
+                        ApprovalDecision::Edit(_) => {
+                            // The user has edited the code on disk so review it again
+                            reload_backend_files(factsheet).map_err(AgentError::Failed)?;
+                            continue;
+                        }
+                        ApprovalDecision::Regenerate => {
+                            self.bug_count = 0;
+                            self.bug_errors = None;
+                            self.attributes.state = AgentState::Discovery;
+                            continue;
+                        }
+                        ApprovalDecision::Abort => {
+                            return Err(Box::new(AgentError::Aborted(
+                                ApprovalGate::RunCode.key().to_string(),
+                            )))
+                        }
                     }
 
-                    // Build and Test Code
+                    // Stage 1: Build the code
                     PrintCommand::UnitTest.print_agent_message(
                         self.attributes.position.as_str(),
                         "Backend Code Unit Testing: building project...",
                     );
-
-                    let web_server_project_path: String = env::var("WEB_SERVER_PROJECT_PATH")
-                        .expect("WEB_SERVER_PROJECT_PATH not found in environment variables");
-
-                    // Build Code
-                    let build_backend_server: std::process::Output = Command::new("cargo")
-                        .arg("build")
-                        .current_dir(web_server_project_path)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .output()
-                        .expect("Failed to build backend application");
-
-                    // Determine if there are any build errors
-                    if build_backend_server.status.success() {
-                        self.bug_count = 0;
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            "Backend Code Unit Testing: Test server build successful...",
-                        );
-                    } else {
-                        let error_arr: Vec<u8> = build_backend_server.stderr;
-                        let error_str: String = String::from_utf8(error_arr).unwrap();
-
-                        // Update error stats
-                        self.bug_count += 1;
-                        self.bug_errors = Some(error_str);
-
-                        // Check for bugs and exit if too many bugs occur
-                        if self.bug_count > 2 {
-                            PrintCommand::Issue.print_agent_message(
-                                self.attributes.position.as_str(),
-                                "Backend Code Unit Testing: Too many bugs found in code",
-                            );
-                            panic!("Error: Too many bugs")
+                    // Compiler suggestions are applied and rebuilt here, so the scan and safety check are not repeated
+                    let mut machine_fix_rounds: u8 = 0;
+                    let (build_backend_server, diagnostics) = loop {
+                        let build_output: std::process::Output =
+                            run_cargo(&["build", "--message-format=json"], cancel).await?;
+                        if build_output.status.success() {
+                            break (build_output, vec![]);
                         }
 
-                        // Extract and Test REST API Endpoints
-
-                        // Extract API Endpoints
-                        let api_endpoints_str: String =
-                            self.call_extract_rest_api_endpoints().await;
-
-                        // Convert API Endpoints into Values
-                        let api_endpoints: Vec<RouteObject> =
-                            serde_json::from_str(api_endpoints_str.as_str())
-                                .expect("Failed to decode API Endpoints");
-
-                        // Define endpoints to check
-                        let check_endpoints: Vec<RouteObject> = api_endpoints
-                            .iter()
-                            .filter(|&route_object| {
-                                route_object.method == "get"
-                                    && route_object.is_route_dynamic == "false"
-                            })
-                            .cloned()
-                            .collect();
-
-                        // Store API Endpoints
-                        factsheet.api_endpoint_schema = Some(check_endpoints.clone());
+                        let diagnostics: Vec<Diagnostic> =
+                            parse_cargo_diagnostics(&String::from_utf8_lossy(&build_output.stdout));
+                        let backend_files: &[ProjectFile] =
+                            factsheet.backend_files.as_deref().unwrap_or_default();
 
-                        // Run backend application
+                        // Apply the fixes the compiler is sure of before asking the LLM
+                        let fixed_files: Vec<ProjectFile> =
+                            apply_machine_fixes(backend_files, &diagnostics);
+                        if fixed_files.is_empty() || machine_fix_rounds >= MAX_MACHINE_FIX_ROUNDS {
+                            break (build_output, diagnostics);
+                        }
+                        machine_fix_rounds += 1;
                         PrintCommand::UnitTest.print_agent_message(
                             self.attributes.position.as_str(),
-                            "Backend Code Unit Testing: Starting web server...",
+                            "Backend Code Unit Testing: Applying compiler suggestions...",
                         );
+                        save_backend_files(factsheet, fixed_files).map_err(AgentError::Failed)?;
+                    };
 
-                        let web_server_project_path: String = env::var("WEB_SERVER_PROJECT_PATH")
-                            .expect("WEB_SERVER_PROJECT_PATH not found in environment variables");
+                    if !build_backend_server.status.success() {
+                        let backend_files: &[ProjectFile] =
+                            factsheet.backend_files.as_deref().unwrap_or_default();
 
-                        // Execute running server
-                        let mut run_backend_server: std::process::Child = Command::new("cargo")
-                            .arg("run")
-                            .current_dir(web_server_project_path)
-                            .stdout(Stdio::piped())
-                            .stderr(Stdio::piped())
-                            .spawn()
-                            .expect("Failed to run backend application");
+                        // Errors cargo could not report as JSON (i.e. a broken manifest) are only on stderr
+                        let error_str: String = match diagnostics.is_empty() {
+                            true => {
+                                String::from_utf8_lossy(&build_backend_server.stderr).to_string()
+                            }
+                            false => compact_errors(&diagnostics, backend_files),
+                        };
 
-                        // Let user know testing on server will take place soon
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            "Backend Code Unit Testing: Launching tests on server in 5 seconds...",
+                        self.report_stage(
+                            UnitTestStage::Build,
+                            vec![format!("{} compiler error(s)", diagnostics.len().max(1))],
                         );
+                        self.record_bugs(error_str)?;
+                        self.attributes.state = AgentState::Working;
+                        continue;
+                    }
+                    self.report_stage(UnitTestStage::Build, vec![]);
 
-                        let seconds_sleep: Duration = Duration::from_secs(5);
-                        time::sleep(seconds_sleep).await;
+                    // Stage 2: Extract the routes from the code
+                    let api_endpoints_str: String = self
+                        .call_extract_rest_api_endpoints(factsheet, cancel)
+                        .await?;
+                    let api_endpoints: Vec<RouteObject> =
+                        match serde_json::from_str(api_endpoints_str.as_str()) {
+                            Ok(api_endpoints) => api_endpoints,
+                            Err(e) => {
+                                let err_msg: String =
+                                    format!("Unable to decode the API endpoints: {}", e);
+                                self.report_stage(
+                                    UnitTestStage::RouteExtraction,
+                                    vec![err_msg.clone()],
+                                );
+                                return Err(Box::new(AgentError::Failed(err_msg)));
+                            }
+                        };
+                    self.report_stage(UnitTestStage::RouteExtraction, vec![]);
 
-                        // Check status code
-                        for endpoint in check_endpoints {
-                            // Confirm URL testing
-                            let testing_msg: String =
-                                format!("Testing endpoint '{}'...", endpoint.route);
-                            PrintCommand::UnitTest.print_agent_message(
-                                self.attributes.position.as_str(),
-                                testing_msg.as_str(),
-                            );
+                    let (check_endpoints, skipped_endpoints) =
+                        probe_targets(&api_endpoints, factsheet.is_auth_incomplete);
+                    for endpoint in skipped_endpoints {
+                        let skip_msg: String = format!(
+                            "Skipping protected endpoint '{} {}' as adding authentication failed",
+                            endpoint.method, endpoint.route
+                        );
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), &skip_msg);
+                    }
+                    factsheet.api_endpoint_schema = Some(api_endpoints.clone());
 
-                            // Create client with timeout
-                            let client: Client = Client::builder()
-                                .timeout(Duration::from_secs(5))
-                                .build()
-                                .unwrap();
-
-                            // Test URL
-                            let url: String = format!("http://localhost:8080{}", endpoint.route);
-                            match check_status_code(&client, &url).await {
-                                Ok(status_code) => {
-                                    if status_code != 200 {
-                                        let err_msg: String = format!(
-                                            "WARNING: Failed to call backend URL endpoint {}",
-                                            endpoint.route
-                                        );
-                                        PrintCommand::Issue.print_agent_message(
-                                            self.attributes.position.as_str(),
-                                            err_msg.as_str(),
-                                        );
-                                    }
-                                }
-                                Err(e) => {
-                                    run_backend_server
-                                        .kill()
-                                        .expect("Failed to kill backend web server");
-                                    let err_msg: String = format!("Error checking backend {}", e);
-                                    PrintCommand::Issue.print_agent_message(
-                                        self.attributes.position.as_str(),
-                                        err_msg.as_str(),
-                                    );
-                                }
+                    // Stage 3: Launch the server
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        "Backend Code Unit Testing: Starting web server...",
+                    );
+                    let port: u16 = allocate_port()?;
+                    let base_url: String = server_base_url(port);
+                    // Replay the recorded external URL responses so the tests do not need the internet
+                    let external_urls: Vec<String> =
+                        factsheet.external_urls.clone().unwrap_or_default();
+                    let stub_server: StubServer =
+                        match StubServer::start(load_url_snapshots(&external_urls)).await {
+                            Ok(stub_server) => stub_server,
+                            Err(e) => {
+                                let err_msg: String =
+                                    format!("Failed to start the external URL stub server: {}", e);
+                                self.report_stage(UnitTestStage::Launch, vec![err_msg.clone()]);
+                                return Err(Box::new(AgentError::Failed(err_msg)));
+                            }
+                        };
+                    let (mut run_backend_server, mut server_log, _sandbox_bridge) =
+                        match spawn_web_server(
+                            port,
+                            &stub_server.env_vars(&external_urls),
+                            &[stub_server.port()],
+                        ) {
+                            Ok(server) => server,
+                            Err(e) => {
+                                let err_msg: String =
+                                    format!("Failed to run backend application: {}", e);
+                                self.report_stage(UnitTestStage::Launch, vec![err_msg.clone()]);
+                                return Err(Box::new(AgentError::Failed(err_msg)));
                             }
+                        };
+
+                    let waiting_msg: String = format!(
+                        "Backend Code Unit Testing: Waiting for the server on {}...",
+                        base_url
+                    );
+                    PrintCommand::UnitTest
+                        .print_agent_message(self.attributes.position.as_str(), &waiting_msg);
+
+                    // A server that crashes or never listens on its port is a bug in the code
+                    match wait_for_server(
+                        &mut run_backend_server,
+                        &mut server_log,
+                        port,
+                        server_ready_timeout(),
+                        cancel,
+                    )
+                    .await
+                    {
+                        Ok(()) => {}
+                        Err(AgentError::Failed(err_msg)) => {
+                            let _ = run_backend_server.kill().await;
+                            self.report_stage(UnitTestStage::Launch, vec![err_msg.clone()]);
+                            self.record_bugs(format!("SERVER_START_FAILURE: {}", err_msg))?;
+                            self.attributes.state = AgentState::Working;
+                            continue;
                         }
+                        Err(e) => return Err(Box::new(e)),
+                    }
+                    self.report_stage(UnitTestStage::Launch, vec![]);
 
-                        save_api_endpoints(&api_endpoints_str);
+                    // Stage 4: Probe the endpoints
+                    let client: Client = Client::builder()
+                        .timeout(Duration::from_secs(5))
+                        .build()
+                        .unwrap();
+                    let mut probe_failures: Vec<String> = vec![];
 
-                        PrintCommand::UnitTest.print_agent_message(
-                            self.attributes.position.as_str(),
-                            "Backend testing complete...",
-                        );
+                    // Log in first so protected routes can be tested as a user
+                    let mut token: Option<String> = None;
+                    if check_endpoints.iter().any(|endpoint| endpoint.is_protected) {
+                        match log_in_test_user(&client, &base_url).await {
+                            Ok(session_token) => token = Some(session_token),
+                            Err(e) => probe_failures
+                                .push(format!("Unable to log in to test protected routes: {}", e)),
+                        }
+                    }
+
+                    let (endpoint_failures, contract_report) = self
+                        .call_probe_endpoints(
+                            &client,
+                            &base_url,
+                            &check_endpoints,
+                            token.as_deref(),
+                            &server_log,
+                            cancel,
+                        )
+                        .await?;
+                    probe_failures.extend(endpoint_failures);
+                    probe_failures.extend(contract_report.iter().map(|contract_mismatch| {
+                        format!(
+                            "{} {} response does not match the schema: {}",
+                            contract_mismatch.method,
+                            contract_mismatch.route,
+                            contract_mismatch.mismatches.join("; ")
+                        )
+                    }));
+                    if let Err(e) = save_run_artifact("contract_report.json", &contract_report) {
+                        let err_msg: String = format!("Unable to save contract_report.json: {}", e);
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), &err_msg);
+                    }
+                    self.report_stage(UnitTestStage::Probe, probe_failures.clone());
 
-                        run_backend_server
-                            .kill()
-                            .expect("Failed to kill backend web server on completion");
+                    // Stage 5: Stop the server
+                    match run_backend_server.kill().await {
+                        Ok(()) => self.report_stage(UnitTestStage::Teardown, vec![]),
+                        Err(e) => {
+                            let err_msg: String =
+                                format!("Failed to kill backend web server: {}", e);
+                            self.report_stage(UnitTestStage::Teardown, vec![err_msg.clone()]);
+                            return Err(Box::new(AgentError::Failed(err_msg)));
+                        }
+                    }
 
-                        self.attributes.state = AgentState::Finished;
+                    if !probe_failures.is_empty() {
+                        self.record_bugs(format!("ENDPOINT_FAILURES: {:?}", probe_failures))?;
+                        self.attributes.state = AgentState::Working;
+                        continue;
                     }
+
+                    save_api_endpoints(&api_endpoints_str);
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        "Backend testing complete...",
+                    );
+                    self.bug_count = 0;
+                    self.attributes.state = AgentState::Finished;
                 }
                 _ => {}
             }
@@ -332,6 +1052,28 @@ impl SpecialFunctions for AgentBackendDeveloper {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::agents::agent_traits::ProjectScope;
+
+    #[test]
+    fn tests_probe_targets() {
+        let mut list_items: RouteObject = RouteObject::for_test("get", "/items");
+        list_items.is_protected = true;
+        let endpoints: Vec<RouteObject> = vec![
+            RouteObject::for_test("post", LOGIN_ROUTE),
+            RouteObject::for_test("get", "/health"),
+            list_items,
+        ];
+
+        let (check, skipped) = probe_targets(&endpoints, false);
+        assert_eq!(check.len(), 2);
+        assert!(skipped.is_empty());
+
+        // Without working auth, protected routes are skipped instead of reported as bugs
+        let (check, skipped) = probe_targets(&endpoints, true);
+        assert_eq!(check, vec![RouteObject::for_test("get", "/health")]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].route, "/items");
+    }
 
     #[tokio::test]
     async fn tests_backend_developer() {
@@ -357,8 +1099,49 @@ mod tests {
 
         agent.attributes.state = AgentState::Discovery;
         agent
-            .execute(&mut factsheet)
+            .execute(&mut factsheet, &CancellationToken::new())
             .await
             .expect("Failed to execute Backend Developer agent");
     }
+
+    #[tokio::test]
+    async fn tests_backend_escalates_unusable_urls() {
+        let mut agent: AgentBackendDeveloper = AgentBackendDeveloper::new();
+        let unusable: UrlProfile = UrlProfile {
+            url: "https://api.broken.com/v1/rates".to_string(),
+            status: Some(500),
+            content_type: None,
+            latency_ms: 12,
+            redirects: vec![],
+            is_auth_required: false,
+            schema: None,
+            sample: None,
+            error: None,
+        };
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: "build a website that shows forex rates".to_string(),
+            project_scope: Some(ProjectScope {
+                is_crud_required: false,
+                is_user_login_and_logout: false,
+                is_external_urls_required: true,
+                ..Default::default()
+            }),
+            external_urls: Some(vec![]),
+            external_url_profiles: Some(vec![unusable.clone()]),
+            ..Default::default()
+        };
+
+        // The Solutions Architect is told which URLs to replace
+        let err: Box<dyn std::error::Error> = agent
+            .execute(&mut factsheet, &CancellationToken::new())
+            .await
+            .expect_err("Backend should escalate without usable URLs");
+        match err.downcast_ref::<AgentError>() {
+            Some(AgentError::Escalation(escalation)) => {
+                assert_eq!(escalation.target_position, "Solutions Architect");
+                assert_eq!(escalation.rejected_urls, vec![unusable.url]);
+            }
+            other => panic!("Expected an escalation, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,270 @@
+use crate::ai_functions::aifunc_database::print_data_model;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::ai_task_request_decoded;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, DataModel, FactSheet, SpecialFunctions};
+
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+
+/// Represents a Database Designer agent
+#[derive(Debug)]
+pub struct AgentDatabaseDesigner {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+}
+
+/// Implementation for a Database Designer agent
+impl AgentDatabaseDesigner {
+    /// Create a new Database Designer agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Designs the entities, fields and relations stored by the backend"
+                .to_string(),
+            position: "Database Designer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self { attributes }
+    }
+
+    /// Design the data model from the project description and scope
+    async fn call_data_model(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
+        let is_crud_required: bool = factsheet
+            .project_scope
+            .is_some_and(|scope| scope.is_crud_required);
+
+        let msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n IS_CRUD_REQUIRED: {} \n REQUIREMENTS: {} \n",
+            factsheet.project_description,
+            is_crud_required,
+            serde_json::to_string(&factsheet.requirements)?
+        );
+
+        let data_model: DataModel = ai_task_request_decoded::<DataModel>(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_data_model),
+            print_data_model,
+            cancel,
+        )
+        .await?;
+
+        for entity in &data_model.entities {
+            let fields: Vec<String> = entity
+                .fields
+                .iter()
+                .map(|field| format!("{}: {}", field.name, field.field_type))
+                .collect();
+            let entity_msg: String = format!("{} {{ {} }}", entity.name, fields.join(", "));
+            PrintCommand::AICall.print_agent_message(&self.attributes.position, &entity_msg);
+        }
+
+        factsheet.data_model = Some(data_model);
+        Ok(())
+    }
+}
+
+/// Represents a struct found in generated Rust code
+#[derive(Debug, PartialEq)]
+struct ParsedStruct {
+    /// Name of the struct
+    name: String,
+    /// Name and type of each field
+    fields: Vec<(String, String)>,
+}
+
+/// Split struct fields on commas that are not inside generic types (i.e. HashMap<u64, String>)
+fn split_top_level_commas(body: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = vec![];
+    let mut depth: i32 = 0;
+    let mut start: usize = 0;
+
+    for (i, c) in body.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&body[start..]);
+    parts
+}
+
+/// Find every struct with named fields in the code
+fn parse_rust_structs(code: &str) -> Vec<ParsedStruct> {
+    let mut structs: Vec<ParsedStruct> = vec![];
+    let mut rest: &str = code;
+
+    while let Some(pos) = rest.find("struct ") {
+        rest = &rest[pos + "struct ".len()..];
+
+        let name: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        let (open, close) = match (rest.find('{'), rest.find(';')) {
+            (Some(open), Some(semicolon)) if semicolon < open => continue,
+            (Some(open), _) => match rest[open..].find('}') {
+                Some(close) => (open, open + close),
+                None => break,
+            },
+            _ => break,
+        };
+
+        // Drop attributes and comments before splitting the fields
+        let body: String = rest[open + 1..close]
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.starts_with("#[") && !line.starts_with("//"))
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let fields: Vec<(String, String)> = split_top_level_commas(&body)
+            .into_iter()
+            .filter_map(|field| {
+                let field: &str = field.trim();
+                let field: &str = field
+                    .strip_prefix("pub(crate)")
+                    .or_else(|| field.strip_prefix("pub"))
+                    .unwrap_or(field);
+                let (field_name, field_type) = field.split_once(':')?;
+                Some((
+                    field_name.trim().to_string(),
+                    field_type.split_whitespace().collect::<String>(),
+                ))
+            })
+            .collect();
+
+        structs.push(ParsedStruct { name, fields });
+        rest = &rest[close..];
+    }
+
+    structs
+}
+
+/// Check that every entity in the data model has a matching struct in the backend code
+/// Returns a description of every missing struct, missing field or mismatched type
+pub fn check_structs_against_model(code: &str, data_model: &DataModel) -> Vec<String> {
+    let structs: Vec<ParsedStruct> = parse_rust_structs(code);
+    let mut mismatches: Vec<String> = vec![];
+
+    for entity in &data_model.entities {
+        let parsed: &ParsedStruct = match structs.iter().find(|s| s.name == entity.name) {
+            Some(parsed) => parsed,
+            None => {
+                mismatches.push(format!(
+                    "Missing struct {} from the data model",
+                    entity.name
+                ));
+                continue;
+            }
+        };
+
+        for field in &entity.fields {
+            let expected_type: String = field.field_type.split_whitespace().collect();
+            match parsed.fields.iter().find(|(name, _)| *name == field.name) {
+                None => mismatches.push(format!(
+                    "Struct {} is missing field {}: {}",
+                    entity.name, field.name, field.field_type
+                )),
+                Some((_, actual_type)) if *actual_type != expected_type => {
+                    mismatches.push(format!(
+                        "Struct {} field {} should be {} but is {}",
+                        entity.name, field.name, field.field_type, actual_type
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    mismatches
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentDatabaseDesigner {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    self.call_data_model(factsheet, cancel).await?;
+                    self.attributes.state = AgentState::Finished;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_check_structs_against_model() {
+        let data_model: DataModel = serde_json::from_str(
+            r#"{
+              "entities": [
+                {
+                  "name": "Item",
+                  "fields": [
+                    { "name": "id", "field_type": "u64" },
+                    { "name": "tags", "field_type": "HashMap<String, u32>" },
+                    { "name": "completed", "field_type": "bool" }
+                  ]
+                },
+                { "name": "User", "fields": [{ "name": "id", "field_type": "u64" }] }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let code: &str = r#"
+            #[derive(Serialize, Deserialize)]
+            pub struct Item {
+                pub id: u64,
+                // Tags and how often they were used
+                pub tags: HashMap<String,u32>,
+                pub completed: String,
+            }
+
+            struct AppState;
+        "#;
+
+        assert_eq!(
+            check_structs_against_model(code, &data_model),
+            vec![
+                "Struct Item field completed should be bool but is String".to_string(),
+                "Missing struct User from the data model".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use crate::ai_functions::aifunc_docs::print_readme_overview;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{ai_task_request, save_project_file};
+use crate::helpers::web_server::web_server_project_path;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, FactSheet, RouteObject, SpecialFunctions};
+
+use async_trait::async_trait;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+/// Title at the top of the generated changelog
+const CHANGELOG_TITLE: &str = "# Changelog";
+
+/// Represents a Technical Writer agent
+#[derive(Debug)]
+pub struct AgentTechnicalWriter {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+    /// The backend code the docs were last written for
+    documented_code: Option<String>,
+    /// The routes the docs were last written for
+    documented_routes: Vec<RouteObject>,
+}
+
+/// Implementation for a Technical Writer agent
+impl AgentTechnicalWriter {
+    /// Create a new Technical Writer agent
+    /// The routes from the previous run are read before the backend overwrites the API schema,
+    /// so the first changelog entry of this run only lists the routes that changed
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Writes the README and changelog for the backend web server".to_string(),
+            position: "Technical Writer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self {
+            attributes,
+            documented_code: None,
+            documented_routes: previous_routes(),
+        }
+    }
+
+    /// Write the README from the project description, environment variables and API schema
+    async fn call_readme(
+        &mut self,
+        factsheet: &FactSheet,
+        backend_code: &str,
+        cancel: &CancellationToken,
+    ) -> Result<String, AgentError> {
+        let env_vars: Vec<String> = find_env_vars(backend_code);
+
+        let msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n ENV_VARS: {:?} \n",
+            factsheet.project_description, env_vars
+        );
+
+        let overview: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_readme_overview),
+            print_readme_overview,
+            cancel,
+        )
+        .await?;
+
+        let routes: &[RouteObject] = factsheet.api_endpoint_schema.as_deref().unwrap_or_default();
+        Ok(format!(
+            "{}\n\n{}\n{}",
+            overview.trim(),
+            render_env_vars(&env_vars),
+            render_api_reference(routes)
+        ))
+    }
+}
+
+/// Routes documented by the previous run, or none if the project has no changelog yet
+fn previous_routes() -> Vec<RouteObject> {
+    if !web_server_project_path().join("CHANGELOG.md").exists() {
+        return vec![];
+    }
+    match env::var("API_SCHEMA_PATH") {
+        Ok(api_schema_path) => read_routes(&PathBuf::from(api_schema_path)),
+        Err(_) => vec![],
+    }
+}
+
+/// Read the routes saved in an API schema file, or none if it is missing or cannot be decoded
+fn read_routes(api_schema_path: &Path) -> Vec<RouteObject> {
+    fs::read_to_string(api_schema_path)
+        .ok()
+        .and_then(|api_schema| serde_json::from_str(&api_schema).ok())
+        .unwrap_or_default()
+}
+
+/// Find the name of every environment variable read by the code, in the order they appear
+fn find_env_vars(code: &str) -> Vec<String> {
+    let mut env_vars: Vec<String> = vec![];
+    let mut rest: &str = code;
+
+    while let Some(pos) = rest.find("env::var(\"") {
+        rest = &rest[pos + "env::var(\"".len()..];
+        if let Some(end) = rest.find('"') {
+            let name: String = rest[..end].to_string();
+            if !env_vars.contains(&name) {
+                env_vars.push(name);
+            }
+        }
+    }
+
+    env_vars
+}
+
+/// Render the environment variables section of the README
+fn render_env_vars(env_vars: &[String]) -> String {
+    let mut section: String = "## Environment Variables\n\n".to_string();
+    if env_vars.is_empty() {
+        section.push_str("The server does not read any environment variables.\n");
+    }
+    for env_var in env_vars {
+        section.push_str(&format!("- `{}`\n", env_var));
+    }
+    section
+}
+
+/// Render a JSON value as a fenced code block
+fn render_json_block(value: &serde_json::Value) -> String {
+    let json: String = serde_json::to_string_pretty(value).unwrap_or_default();
+    format!("```json\n{}\n```\n", json)
+}
+
+/// Render the API reference section of the README with an example request and response per route
+fn render_api_reference(routes: &[RouteObject]) -> String {
+    let mut section: String = "\n## API Reference\n".to_string();
+
+    for route in routes {
+        section.push_str(&format!(
+            "\n### {} {}\n\n",
+            route.method.to_uppercase(),
+            route.route
+        ));
+        if !route.request_body.is_null() {
+            section.push_str("Example request body:\n\n");
+            section.push_str(&render_json_block(&route.request_body));
+            section.push('\n');
+        }
+        section.push_str("Example response:\n\n");
+        section.push_str(&render_json_block(&route.response));
+    }
+
+    section
+}
+
+/// Describe how the routes changed since the docs were last written
+fn changelog_entry(iteration: usize, previous: &[RouteObject], current: &[RouteObject]) -> String {
+    let same_route = |a: &RouteObject, b: &RouteObject| {
+        a.method.eq_ignore_ascii_case(&b.method) && a.route == b.route
+    };
+
+    let mut changes: Vec<String> = vec![];
+    for route in current {
+        if !previous.iter().any(|old| same_route(old, route)) {
+            changes.push(format!(
+                "- Added `{} {}`",
+                route.method.to_uppercase(),
+                route.route
+            ));
+        }
+    }
+    for route in previous {
+        if !current.iter().any(|new| same_route(new, route)) {
+            changes.push(format!(
+                "- Removed `{} {}`",
+                route.method.to_uppercase(),
+                route.route
+            ));
+        }
+    }
+    if changes.is_empty() {
+        changes.push("- Updated the backend code without changing the API".to_string());
+    }
+
+    format!("## Iteration {}\n\n{}\n", iteration, changes.join("\n"))
+}
+
+/// Add a new entry to the top of the changelog, after its title
+fn prepend_changelog_entry(changelog: &str, entry: &str) -> String {
+    let previous_entries: &str = changelog
+        .strip_prefix(CHANGELOG_TITLE)
+        .unwrap_or(changelog)
+        .trim();
+
+    if previous_entries.is_empty() {
+        format!("{}\n\n{}", CHANGELOG_TITLE, entry)
+    } else {
+        format!("{}\n\n{}\n{}\n", CHANGELOG_TITLE, entry, previous_entries)
+    }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentTechnicalWriter {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    /// The docs are written again whenever the backend code changes
+    fn is_outdated(&self, factsheet: &FactSheet) -> bool {
+        factsheet.backend_code.is_some() && factsheet.backend_code != self.documented_code
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    if factsheet.backend_code.is_none() {
+                        return Err(Box::new(AgentError::Failed(
+                            "There is no backend code to document".to_string(),
+                        )));
+                    }
+                    self.attributes.state = AgentState::Working;
+                }
+
+                AgentState::Working => {
+                    let backend_code: String = factsheet.backend_code.clone().unwrap_or_default();
+                    let routes: Vec<RouteObject> =
+                        factsheet.api_endpoint_schema.clone().unwrap_or_default();
+
+                    let readme: String = self.call_readme(factsheet, &backend_code, cancel).await?;
+                    save_project_file("README.md", &readme).map_err(AgentError::Failed)?;
+
+                    // Every time the docs are written counts as a new iteration of the project
+                    let changelog: String =
+                        fs::read_to_string(web_server_project_path().join("CHANGELOG.md"))
+                            .unwrap_or_default();
+                    let iteration: usize = changelog.matches("## Iteration ").count() + 1;
+                    let entry: String =
+                        changelog_entry(iteration, &self.documented_routes, &routes);
+                    save_project_file("CHANGELOG.md", &prepend_changelog_entry(&changelog, &entry))
+                        .map_err(AgentError::Failed)?;
+
+                    PrintCommand::AICall.print_agent_message(
+                        self.attributes.position.as_str(),
+                        "Documentation: README.md and CHANGELOG.md written",
+                    );
+
+                    self.documented_code = Some(backend_code);
+                    self.documented_routes = routes;
+                    self.attributes.state = AgentState::Finished;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_find_env_vars() {
+        let code: &str = r#"
+            let port = env::var("PORT").unwrap_or("8080".to_string());
+            let key = std::env::var("API_KEY").unwrap();
+            let port_again = env::var("PORT");
+        "#;
+
+        assert_eq!(find_env_vars(code), vec!["PORT", "API_KEY"]);
+    }
+
+    #[test]
+    fn tests_changelog_entries() {
+        let previous: Vec<RouteObject> = vec![
+            RouteObject::for_test("get", "/item"),
+            RouteObject::for_test("get", "/old"),
+        ];
+        let current: Vec<RouteObject> = vec![
+            RouteObject::for_test("GET", "/item"),
+            RouteObject::for_test("post", "/item"),
+        ];
+
+        let entry: String = changelog_entry(2, &previous, &current);
+        assert_eq!(
+            entry,
+            "## Iteration 2\n\n- Added `POST /item`\n- Removed `GET /old`\n"
+        );
+
+        let changelog: String = prepend_changelog_entry("", &changelog_entry(1, &[], &[]));
+        let changelog: String = prepend_changelog_entry(&changelog, &entry);
+        assert!(changelog.starts_with("# Changelog\n\n## Iteration 2"));
+        assert!(changelog.contains("## Iteration 1\n\n- Updated the backend code"));
+    }
+
+    #[test]
+    fn tests_read_routes() {
+        let api_schema_path: PathBuf = env::temp_dir().join("lazy_agents_test_api_schema.json");
+        let routes: Vec<RouteObject> = vec![RouteObject::for_test("get", "/item")];
+        fs::write(&api_schema_path, serde_json::to_string(&routes).unwrap()).unwrap();
+
+        // A second run only lists the routes that changed since the first
+        let previous: Vec<RouteObject> = read_routes(&api_schema_path);
+        assert_eq!(previous, routes);
+        assert_eq!(
+            changelog_entry(2, &previous, &routes),
+            "## Iteration 2\n\n- Updated the backend code without changing the API\n"
+        );
+
+        fs::write(&api_schema_path, "not json").unwrap();
+        assert!(read_routes(&api_schema_path).is_empty());
+        assert!(read_routes(Path::new("/nonexistent/api_schema.json")).is_empty());
+        let _ = fs::remove_file(api_schema_path);
+    }
+
+    #[test]
+    fn tests_render_api_reference() {
+        let mut create_item: RouteObject = RouteObject::for_test("post", "/item");
+        create_item.request_body = serde_json::json!({ "name": "milk" });
+
+        let reference: String =
+            render_api_reference(&[RouteObject::for_test("get", "/item/{id}"), create_item]);
+        assert!(reference.contains("### GET /item/{id}"));
+        assert!(reference.contains("### POST /item\n\nExample request body:"));
+        assert!(reference.contains("\"name\": \"milk\""));
+        assert_eq!(reference.matches("Example response:").count(), 2);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::ai_functions::aifunc_frontend::print_frontend_files;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{ai_task_request_decoded, save_frontend_files};
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{
+    AgentError, FactSheet, ProjectFile, RouteObject, SpecialFunctions,
+};
+
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of times the pages are regenerated when they call routes that do not exist
+const MAX_FETCH_FIXES: u8 = 2;
+
+/// Represents a Frontend Developer agent
+#[derive(Debug)]
+pub struct AgentFrontendDeveloper {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+    /// Fetch calls from the last generated pages that do not match any route
+    fetch_errors: Vec<String>,
+    /// Total number of times the pages have been regenerated to fix fetch calls
+    fix_count: u8,
+}
+
+/// Implementation for a Frontend Developer agent
+impl AgentFrontendDeveloper {
+    /// Create a new Frontend Developer agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Develops static frontend pages that use the backend API".to_string(),
+            position: "Frontend Developer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self {
+            attributes,
+            fetch_errors: vec![],
+            fix_count: 0,
+        }
+    }
+
+    /// Generate the frontend pages from the project description and API schema
+    async fn call_frontend_files(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
+        let mut msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n API_ENDPOINT_SCHEMA: {} \n",
+            factsheet.project_description,
+            serde_json::to_string(&factsheet.api_endpoint_schema)?
+        );
+        if !self.fetch_errors.is_empty() {
+            msg_context.push_str(&format!(
+                "PREVIOUS_FILES: {} \n FETCH_ERRORS: {:?} \n",
+                serde_json::to_string(&factsheet.frontend_files)?,
+                self.fetch_errors
+            ));
+        }
+
+        let frontend_files: Vec<ProjectFile> = ai_task_request_decoded::<Vec<ProjectFile>>(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_frontend_files),
+            print_frontend_files,
+            cancel,
+        )
+        .await?;
+
+        factsheet.frontend_files = Some(frontend_files);
+        Ok(())
+    }
+}
+
+/// Find the URL passed to every fetch call that uses a string or template literal
+fn extract_fetch_targets(source: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+    let mut rest: &str = source;
+
+    while let Some(pos) = rest.find("fetch(") {
+        rest = &rest[pos + "fetch(".len()..];
+        let argument: &str = rest.trim_start();
+
+        let quote: char = match argument.chars().next() {
+            Some(quote @ ('"' | '\'' | '`')) => quote,
+            _ => continue,
+        };
+        let literal: &str = &argument[1..];
+        if let Some(end) = literal.find(quote) {
+            targets.push(literal[..end].to_string());
+        }
+    }
+
+    targets
+}
+
+/// Reduce a fetch URL to its path (i.e. "http://localhost:8080/item?x=1" becomes "/item")
+fn fetch_target_path(target: &str) -> &str {
+    let mut path: &str = target;
+    for scheme in ["http://", "https://"] {
+        if let Some(without_scheme) = path.strip_prefix(scheme) {
+            path = without_scheme
+                .find('/')
+                .map_or("/", |start| &without_scheme[start..]);
+        }
+    }
+    path.split(['?', '#']).next().unwrap_or(path)
+}
+
+/// Check if a fetch path matches a route, treating {param} and ${expression} segments as wildcards
+fn is_route_match(fetch_path: &str, route: &str) -> bool {
+    let fetch_segments: Vec<&str> = fetch_path.trim_matches('/').split('/').collect();
+    let route_segments: Vec<&str> = route.trim_matches('/').split('/').collect();
+
+    fetch_segments.len() == route_segments.len()
+        && fetch_segments
+            .iter()
+            .zip(route_segments.iter())
+            .all(|(fetch_segment, route_segment)| {
+                fetch_segment == route_segment
+                    || route_segment.starts_with('{')
+                    || fetch_segment.contains("${")
+            })
+}
+
+/// Find every fetch call in the files that does not target an existing route
+fn find_invalid_fetches(files: &[ProjectFile], routes: &[RouteObject]) -> Vec<String> {
+    let mut invalid: Vec<String> = vec![];
+
+    for file in files {
+        for target in extract_fetch_targets(&file.content) {
+            let path: &str = fetch_target_path(&target);
+            if !routes
+                .iter()
+                .any(|route| is_route_match(path, &route.route))
+            {
+                invalid.push(format!(
+                    "{}: fetch(\"{}\") does not match any route",
+                    file.path, target
+                ));
+            }
+        }
+    }
+
+    invalid
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentFrontendDeveloper {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    if factsheet.api_endpoint_schema.is_none() {
+                        return Err(Box::new(AgentError::Failed(
+                            "There is no API endpoint schema to build pages for".to_string(),
+                        )));
+                    }
+
+                    self.fetch_errors.clear();
+                    self.fix_count = 0;
+                    self.attributes.state = AgentState::Working;
+                }
+
+                AgentState::Working => {
+                    self.call_frontend_files(factsheet, cancel).await?;
+                    self.attributes.state = AgentState::UnitTesting;
+                }
+
+                AgentState::UnitTesting => {
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        "Frontend Unit Testing: Checking fetch calls against API routes...",
+                    );
+
+                    let files: &[ProjectFile] =
+                        factsheet.frontend_files.as_deref().unwrap_or_default();
+                    let routes: &[RouteObject] =
+                        factsheet.api_endpoint_schema.as_deref().unwrap_or_default();
+                    self.fetch_errors = find_invalid_fetches(files, routes);
+
+                    if self.fetch_errors.is_empty() {
+                        save_frontend_files(files).map_err(AgentError::Failed)?;
+                        PrintCommand::UnitTest.print_agent_message(
+                            self.attributes.position.as_str(),
+                            "Frontend Unit Testing: All fetch calls match API routes",
+                        );
+                        self.attributes.state = AgentState::Finished;
+                        continue;
+                    }
+
+                    for fetch_error in &self.fetch_errors {
+                        PrintCommand::Issue
+                            .print_agent_message(self.attributes.position.as_str(), fetch_error);
+                    }
+
+                    self.fix_count += 1;
+                    if self.fix_count > MAX_FETCH_FIXES {
+                        return Err(Box::new(AgentError::Failed(
+                            "Frontend pages still call routes that do not exist".to_string(),
+                        )));
+                    }
+                    self.attributes.state = AgentState::Working;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_find_invalid_fetches() {
+        let routes: Vec<RouteObject> = vec![
+            RouteObject::for_test("get", "/item"),
+            RouteObject::for_test("get", "/item/{id}"),
+        ];
+        let files: Vec<ProjectFile> = vec![ProjectFile {
+            path: "app.js".to_string(),
+            content: r#"
+              fetch("/item");
+              fetch( `/item/${item.id}`, { method: "DELETE" });
+              fetch('http://localhost:8080/item?page=2');
+              fetch("/items/all");
+              fetch(url);
+            "#
+            .to_string(),
+        }];
+
+        let invalid: Vec<String> = find_invalid_fetches(&files, &routes);
+
+        assert_eq!(
+            invalid,
+            vec!["app.js: fetch(\"/items/all\") does not match any route".to_string()]
+        );
+    }
+}
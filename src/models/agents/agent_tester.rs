@@ -0,0 +1,337 @@
+use crate::ai_functions::aifunc_tester::print_integration_tests;
+use crate::helpers::command_line::PrintCommand;
+use crate::helpers::general::{ai_task_request, save_integration_tests};
+use crate::helpers::sandbox::{PortForward, SandboxPhase};
+use crate::helpers::scope_features::{active_features, describe_features, ScopeFeature};
+use crate::helpers::stub_server::{load_url_snapshots, StubServer};
+use crate::helpers::web_server::{
+    allocate_port, find_runtime_errors, run_cargo, run_cargo_with_env, server_base_url,
+    server_ready_timeout, spawn_web_server, wait_for_server, web_server_project_path,
+    INTEGRATION_TEST_TARGET,
+};
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agents::agent_traits::{AgentError, Escalation, FactSheet, SpecialFunctions};
+
+use async_trait::async_trait;
+use std::fs;
+use std::process::Output;
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of times the tests are rewritten when they do not compile
+const MAX_TEST_FIXES: u8 = 2;
+
+/// Position of the agent that fixes the server when the tests fail
+const BACKEND_AGENT_POSITION: &str = "Backend Developer";
+
+/// Represents an Integration Tester agent
+#[derive(Debug)]
+pub struct AgentIntegrationTester {
+    /// The attributes belonging to the agent
+    attributes: BasicAgent,
+    /// Compile errors from the last tests that were written
+    compile_errors: Option<String>,
+    /// Total number of times the tests have been rewritten to fix compile errors
+    fix_count: u8,
+}
+
+/// Implementation for an Integration Tester agent
+impl AgentIntegrationTester {
+    /// Create a new Integration Tester agent
+    pub fn new() -> Self {
+        let attributes: BasicAgent = BasicAgent {
+            objective: "Writes and runs integration tests against the backend web server"
+                .to_string(),
+            position: "Integration Tester".to_string(),
+            state: AgentState::Discovery,
+            memory: vec![],
+        };
+
+        Self {
+            attributes,
+            compile_errors: None,
+            fix_count: 0,
+        }
+    }
+
+    /// Write the integration tests from the API endpoint schema and data model
+    async fn call_integration_tests(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), AgentError> {
+        // The tests can only use crates the web server project already depends on
+        let dependencies: String =
+            fs::read_to_string(web_server_project_path().join("Cargo.toml")).unwrap_or_default();
+
+        let mut msg_context: String = format!(
+            "PROJECT_DESCRIPTION: {} \n API_ENDPOINT_SCHEMA: {} \n DATA_MODEL: {} \n DEPENDENCIES: {} \n",
+            factsheet.project_description,
+            serde_json::to_string(&factsheet.api_endpoint_schema).unwrap_or_default(),
+            serde_json::to_string(&factsheet.data_model).unwrap_or_default(),
+            dependencies
+        );
+        // Features in the project scope need their own tests (i.e. pagination or rate limits)
+        if let Some(project_scope) = &factsheet.project_scope {
+            let features: Vec<ScopeFeature> = active_features(project_scope);
+            if !features.is_empty() {
+                msg_context.push_str(&format!(
+                    "SCOPE_FEATURES: {} \n",
+                    describe_features(&features, |feature| feature.test_instruction)
+                ));
+            }
+        }
+        if let Some(compile_errors) = &self.compile_errors {
+            msg_context.push_str(&format!(
+                "PREVIOUS_TESTS: {:?} \n TEST_COMPILE_ERRORS: {:?} \n",
+                factsheet.integration_tests, compile_errors
+            ));
+        }
+
+        let ai_response: String = ai_task_request(
+            msg_context,
+            &self.attributes.position,
+            get_function_string!(print_integration_tests),
+            print_integration_tests,
+            cancel,
+        )
+        .await?;
+
+        factsheet.integration_tests = Some(ai_response);
+        Ok(())
+    }
+
+    /// Run the integration tests against a freshly started web server
+    /// Returns the test output and any runtime errors the server logged while the tests ran
+    async fn run_integration_tests(
+        &self,
+        factsheet: &FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(Output, Vec<String>), AgentError> {
+        let port: u16 = allocate_port().map_err(|e| AgentError::Failed(e.to_string()))?;
+        let base_url: String = server_base_url(port);
+
+        // The server calls the stub server instead of the real external URLs, so the tests run offline
+        let external_urls: Vec<String> = factsheet.external_urls.clone().unwrap_or_default();
+        let stub_server: StubServer = StubServer::start(load_url_snapshots(&external_urls))
+            .await
+            .map_err(|e| {
+                AgentError::Failed(format!(
+                    "Failed to start the external URL stub server: {}",
+                    e
+                ))
+            })?;
+        let (mut run_backend_server, mut server_log, _sandbox_bridge) = spawn_web_server(
+            port,
+            &stub_server.env_vars(&external_urls),
+            &[stub_server.port()],
+        )
+        .map_err(|e| AgentError::Failed(format!("Failed to run backend application: {}", e)))?;
+
+        let waiting_msg: String = format!(
+            "Integration Testing: Waiting for the server on {}...",
+            base_url
+        );
+        PrintCommand::UnitTest.print_agent_message(self.attributes.position.as_str(), &waiting_msg);
+
+        // A server that does not start is a bug in the server, so the backend has to fix it
+        match wait_for_server(
+            &mut run_backend_server,
+            &mut server_log,
+            port,
+            server_ready_timeout(),
+            cancel,
+        )
+        .await
+        {
+            Ok(()) => {}
+            Err(AgentError::Failed(err_msg)) => {
+                return Err(AgentError::Escalation(Escalation {
+                    target_position: BACKEND_AGENT_POSITION.to_string(),
+                    reason: format!("SERVER_START_FAILURE: {}", err_msg),
+                    rejected_urls: vec![],
+                }))
+            }
+            Err(e) => return Err(e),
+        }
+
+        let log_mark: usize = server_log.mark();
+        let test_output: Result<Output, AgentError> = run_cargo_with_env(
+            &["test", "--test", INTEGRATION_TEST_TARGET],
+            &[("API_BASE_URL", base_url.as_str())],
+            Some(SandboxPhase::Run),
+            &[PortForward::Outbound(port)],
+            cancel,
+        )
+        .await;
+        let runtime_errors: Vec<String> = find_runtime_errors(&server_log.lines_since(log_mark));
+
+        run_backend_server
+            .kill()
+            .await
+            .expect("Failed to kill backend web server on completion");
+
+        Ok((test_output?, runtime_errors))
+    }
+}
+
+/// Keep only the details of the failing tests from the cargo test output
+fn summarize_test_failures(stdout: &str) -> String {
+    // cargo prints the output of every failing test after the first "failures:" line
+    match stdout.split_once("\nfailures:\n") {
+        Some((_, failures)) => failures
+            .split("\ntest result:")
+            .next()
+            .unwrap_or(failures)
+            .trim()
+            .to_string(),
+        None => stdout.trim().to_string(),
+    }
+}
+
+#[async_trait]
+impl SpecialFunctions for AgentIntegrationTester {
+    fn get_attributes_from_agent(&self) -> &BasicAgent {
+        &self.attributes
+    }
+
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+        &mut self.attributes
+    }
+
+    async fn execute(
+        &mut self,
+        factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        while self.attributes.state != AgentState::Finished {
+            match self.attributes.state {
+                AgentState::Discovery => {
+                    if factsheet.api_endpoint_schema.is_none() {
+                        return Err(Box::new(AgentError::Failed(
+                            "There is no API endpoint schema to write tests for".to_string(),
+                        )));
+                    }
+
+                    self.compile_errors = None;
+                    self.fix_count = 0;
+                    self.attributes.state = AgentState::Working;
+                }
+
+                AgentState::Working => {
+                    self.call_integration_tests(factsheet, cancel).await?;
+                    let tests: &str = factsheet.integration_tests.as_deref().unwrap_or_default();
+                    save_integration_tests(tests).map_err(|e| AgentError::Failed(e.to_string()))?;
+                    self.attributes.state = AgentState::UnitTesting;
+                }
+
+                AgentState::UnitTesting => {
+                    PrintCommand::UnitTest.print_agent_message(
+                        self.attributes.position.as_str(),
+                        "Integration Testing: building tests...",
+                    );
+
+                    // Build first so broken tests are fixed here instead of blamed on the server
+                    let build_output: Output = run_cargo(
+                        &["test", "--no-run", "--test", INTEGRATION_TEST_TARGET],
+                        cancel,
+                    )
+                    .await?;
+                    if !build_output.status.success() {
+                        let stderr: String =
+                            String::from_utf8_lossy(&build_output.stderr).to_string();
+                        let test_file: String = format!("tests/{}.rs", INTEGRATION_TEST_TARGET);
+
+                        // The server itself does not compile, so the backend has to fix it
+                        if !stderr.contains(&test_file) {
+                            return Err(Box::new(AgentError::Escalation(Escalation {
+                                target_position: BACKEND_AGENT_POSITION.to_string(),
+                                reason: format!("BUILD_ERRORS: {}", stderr),
+                                rejected_urls: vec![],
+                            })));
+                        }
+
+                        self.fix_count += 1;
+                        if self.fix_count > MAX_TEST_FIXES {
+                            return Err(Box::new(AgentError::Failed(
+                                "Integration tests still do not compile".to_string(),
+                            )));
+                        }
+                        PrintCommand::Issue.print_agent_message(
+                            self.attributes.position.as_str(),
+                            "Integration Testing: tests do not compile, rewriting them...",
+                        );
+                        self.compile_errors = Some(stderr);
+                        self.attributes.state = AgentState::Working;
+                        continue;
+                    }
+
+                    let (test_output, runtime_errors) =
+                        self.run_integration_tests(factsheet, cancel).await?;
+                    if test_output.status.success() {
+                        PrintCommand::UnitTest.print_agent_message(
+                            self.attributes.position.as_str(),
+                            "Integration Testing: All integration tests passed",
+                        );
+                        self.attributes.state = AgentState::Finished;
+                        continue;
+                    }
+
+                    // Send the failures into the backend fix loop
+                    let mut failures: String =
+                        summarize_test_failures(&String::from_utf8_lossy(&test_output.stdout));
+                    if !runtime_errors.is_empty() {
+                        failures
+                            .push_str(&format!(" \n SERVER_OUTPUT: {}", runtime_errors.join("\n")));
+                    }
+                    PrintCommand::Issue
+                        .print_agent_message(self.attributes.position.as_str(), failures.as_str());
+                    return Err(Box::new(AgentError::Escalation(Escalation {
+                        target_position: BACKEND_AGENT_POSITION.to_string(),
+                        reason: format!("INTEGRATION_TEST_FAILURES: {}", failures),
+                        rejected_urls: vec![],
+                    })));
+                }
+
+                // Default to Finished state
+                _ => {
+                    self.attributes.state = AgentState::Finished;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_summarize_test_failures() {
+        let stdout: &str = "
+running 2 tests
+test tests_item_lifecycle ... FAILED
+test tests_list_items ... ok
+
+failures:
+
+---- tests_item_lifecycle stdout ----
+thread 'tests_item_lifecycle' panicked at tests/api_tests.rs:20:5:
+assertion `left == right` failed
+  left: 404
+ right: 200
+
+failures:
+    tests_item_lifecycle
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+";
+
+        let failures: String = summarize_test_failures(stdout);
+        assert!(failures.starts_with("---- tests_item_lifecycle stdout ----"));
+        assert!(failures.contains("left: 404"));
+        assert!(!failures.contains("test result"));
+        assert!(!failures.contains("tests_list_items"));
+    }
+}
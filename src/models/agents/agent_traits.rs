@@ -1,7 +1,9 @@
-use crate::models::agent_basic::basic_agent::BasicAgent;
+use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
+use crate::models::agent_basic::basic_traits::BasicTraits;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use tokio_util::sync::CancellationToken;
 
 /// Represents the schema for the API endpoints
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -16,12 +18,83 @@ pub struct RouteObject {
     pub response: serde_json::Value,
     /// The route path
     pub route: String,
+    /// Flag to determine if the route requires a logged in user
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_protected: bool,
+}
+
+#[cfg(test)]
+impl RouteObject {
+    /// Build an unprotected route without a request body or response for tests
+    pub fn for_test(method: &str, route: &str) -> Self {
+        RouteObject {
+            is_route_dynamic: route.contains('{').to_string(),
+            method: method.to_string(),
+            request_body: serde_json::Value::Null,
+            response: serde_json::Value::Null,
+            route: route.to_string(),
+            is_protected: false,
+        }
+    }
+}
+
+/// Read a flag written either as a bool or as a string (i.e. "true"), as the LLM wraps every value in quotes
+fn deserialize_flag<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(flag) => Ok(flag),
+        serde_json::Value::String(flag) => Ok(flag.trim().eq_ignore_ascii_case("true")),
+        _ => Ok(false),
+    }
+}
+
+/// Read a choice in any case and with spaces or dashes between words (i.e. "SQLite", "JSON File" or "WebSockets"), as the LLM does not keep to snake_case
+fn deserialize_choice<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let choice: serde_json::Value = match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(choice) => {
+            serde_json::Value::String(choice.trim().to_lowercase().replace([' ', '-'], "_"))
+        }
+        choice => choice,
+    };
+    serde_json::from_value(choice).map_err(serde::de::Error::custom)
+}
+
+/// Represents where the web server stores its data
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistenceType {
+    /// Data is only kept in memory
+    None,
+    /// Data is stored in a JSON file database (the code template)
+    #[default]
+    #[serde(alias = "json")]
+    JsonFile,
+    /// Data is stored in a SQLite database
+    Sqlite,
+}
+
+/// Represents how the web server pushes updates to clients
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RealtimeType {
+    /// Clients only receive data they request
+    #[default]
+    None,
+    /// Updates are pushed over websockets
+    #[serde(alias = "websocket")]
+    Websockets,
+    /// Updates are pushed as server-sent events
+    Sse,
 }
 
 /// Represents the scope of the project
 /// This will be unknown when we first create a FactSheet
 /// This will be generated only after the agent does the initial work
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+/// Fields added after the first three default when missing, so older factsheets can still be read
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
 pub struct ProjectScope {
     /// Flag to determine if CRUD will be used
     pub is_crud_required: bool,
@@ -29,10 +102,220 @@ pub struct ProjectScope {
     pub is_user_login_and_logout: bool,
     /// Flag to determine if external URLS will be used
     pub is_external_urls_required: bool,
+    /// Where the data is stored
+    #[serde(default, deserialize_with = "deserialize_choice")]
+    pub persistence: PersistenceType,
+    /// How updates are pushed to clients
+    #[serde(default, deserialize_with = "deserialize_choice")]
+    pub realtime: RealtimeType,
+    /// Flag to determine if lists are returned a page at a time
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_pagination_required: bool,
+    /// Flag to determine if users can upload files
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_file_upload_required: bool,
+    /// Flag to determine if work runs on a schedule in the background
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_scheduled_jobs_required: bool,
+    /// Flag to determine if clients are limited in how many requests they can make
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_rate_limiting_required: bool,
+    /// Flag to determine if some users are admins with extra permissions
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_admin_roles_required: bool,
+    /// Flag to determine if responses are translated into the user's language
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub is_i18n_required: bool,
 }
 
-/// Represents a set of rules that the agents should follow
+/// Implementation of ProjectScope
+impl ProjectScope {
+    /// Toggle a scope flag by its short name (crud, login, urls, pagination, uploads, jobs, ratelimit, admin or i18n)
+    /// or set a choice with name=value (persistence=none|json_file|sqlite or realtime=none|websockets|sse)
+    /// Returns false if the flag name or value is not recognised
+    pub fn toggle_flag(&mut self, flag: &str) -> bool {
+        let flag: String = flag.trim().to_lowercase();
+        if let Some((name, value)) = flag.split_once('=') {
+            let value: serde_json::Value = serde_json::Value::String(value.trim().to_string());
+            return match name.trim() {
+                "persistence" => serde_json::from_value(value)
+                    .map(|persistence| self.persistence = persistence)
+                    .is_ok(),
+                "realtime" => serde_json::from_value(value)
+                    .map(|realtime| self.realtime = realtime)
+                    .is_ok(),
+                _ => false,
+            };
+        }
+
+        let scope_flag: &mut bool = match flag.as_str() {
+            "crud" => &mut self.is_crud_required,
+            "login" => &mut self.is_user_login_and_logout,
+            "urls" => &mut self.is_external_urls_required,
+            "pagination" => &mut self.is_pagination_required,
+            "uploads" => &mut self.is_file_upload_required,
+            "jobs" => &mut self.is_scheduled_jobs_required,
+            "ratelimit" => &mut self.is_rate_limiting_required,
+            "admin" => &mut self.is_admin_roles_required,
+            "i18n" => &mut self.is_i18n_required,
+            _ => return false,
+        };
+        *scope_flag = !*scope_flag;
+        true
+    }
+
+    /// Flag to determine if users need to log in, as admin roles are given to logged in users
+    pub fn is_auth_required(&self) -> bool {
+        self.is_user_login_and_logout || self.is_admin_roles_required
+    }
+}
+
+/// Represents how serious a finding about the generated code is
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Represents a problem found by the static security scan of the backend code
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SecurityFinding {
+    /// Identifier of the rule that found the problem (i.e. SEC001)
+    pub rule_id: String,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Path of the backend file the problem is in
+    #[serde(default)]
+    pub file: String,
+    /// Line of the file the problem is on (starting at 1)
+    pub line: usize,
+    /// What the problem is
+    pub message: String,
+}
+
+/// Represents a single comment from a code review of the backend code
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReviewComment {
+    /// How serious the issue is
+    pub severity: Severity,
+    /// The area the comment covers (security, error_handling or spec_adherence)
+    pub category: String,
+    /// Where in the code the issue is (i.e. a function name or route)
+    pub location: String,
+    /// Why the code should be changed
+    pub rationale: String,
+}
+
+/// Represents a requirement clarified with the user, or assumed when nobody could be asked
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Requirement {
+    /// What the requirement is about (i.e. data_source, auth, persistence)
+    pub topic: String,
+    /// The question that was asked about the user's request
+    pub question: String,
+    /// The answer given by the user, or the assumption made
+    pub answer: String,
+    /// Flag to determine if the answer was assumed rather than given by the user
+    pub is_assumption: bool,
+}
+
+/// Represents a field of an entity in the data model
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EntityField {
+    /// Name of the field (snake_case)
+    pub name: String,
+    /// Rust type of the field (i.e. "u64", "String", "Option<String>")
+    pub field_type: String,
+}
+
+/// Represents a relation from one entity to another
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EntityRelation {
+    /// Kind of relation (one_to_one, one_to_many, many_to_one or many_to_many)
+    pub kind: String,
+    /// Name of the related entity
+    pub entity: String,
+    /// Field holding the id of the related entity
+    pub via_field: String,
+}
+
+/// Represents something stored by the web server (i.e. an item or a user)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Entity {
+    /// Name of the entity, used as the struct name (PascalCase)
+    pub name: String,
+    /// Fields of the entity
+    pub fields: Vec<EntityField>,
+    /// Relations to other entities
+    #[serde(default)]
+    pub relations: Vec<EntityRelation>,
+}
+
+/// Represents the data model stored in the web server's JSON database
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DataModel {
+    /// Every entity in the data model
+    pub entities: Vec<Entity>,
+}
+
+/// Represents what was learned about an external URL by calling it
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UrlProfile {
+    /// The URL that was called
+    pub url: String,
+    /// The status code of the final response, or None if the request failed
+    pub status: Option<u16>,
+    /// The content type of the final response, if any
+    pub content_type: Option<String>,
+    /// Time taken to receive the whole response in milliseconds, following any redirects
+    pub latency_ms: u64,
+    /// URLs the request was redirected to, in order
+    pub redirects: Vec<String>,
+    /// Flag to determine if the URL refused the request without credentials (401 or 403)
+    pub is_auth_required: bool,
+    /// Shape of the JSON response with Rust type hints (i.e. {"price": "f64", "symbol": "String"})
+    pub schema: Option<serde_json::Value>,
+    /// The JSON response with long arrays and strings shortened
+    pub sample: Option<serde_json::Value>,
+    /// Why the request failed, if it did
+    pub error: Option<String>,
+}
+
+/// Implementation of UrlProfile
+impl UrlProfile {
+    /// Flag to determine if the backend can use the URL
+    pub fn is_usable(&self) -> bool {
+        self.status == Some(200)
+    }
+
+    /// Describe why the backend cannot use the URL, or None if it can
+    pub fn problem(&self) -> Option<String> {
+        match (&self.error, self.status) {
+            (Some(error), _) => Some(format!("request failed: {}", error)),
+            (None, Some(200)) => None,
+            (None, Some(status)) if self.is_auth_required => {
+                Some(format!("requires authentication (status {})", status))
+            }
+            (None, Some(status)) => Some(format!("responded with status {}", status)),
+            (None, None) => Some("no response".to_string()),
+        }
+    }
+}
+
+/// Represents a file written by an agent into the web server project
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProjectFile {
+    /// Path of the file, relative to the directory it is written into
+    pub path: String,
+    /// Contents of the file
+    pub content: String,
+}
+
+/// Represents a set of rules that the agents should follow
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct FactSheet {
     /// Description of the project
     pub project_description: String,
@@ -40,10 +323,85 @@ pub struct FactSheet {
     pub project_scope: Option<ProjectScope>,
     /// External URLS related to the project
     pub external_urls: Option<Vec<String>>,
-    /// Backend code related to the project
+    /// What was learned about each external URL when it was checked (payload shape, latency, auth)
+    #[serde(default)]
+    pub external_url_profiles: Option<Vec<UrlProfile>>,
+    /// Backend code related to the project (every backend file combined, see backend_files)
     pub backend_code: Option<String>,
     /// API endpoint schema for the project
     pub api_endpoint_schema: Option<Vec<RouteObject>>,
+    /// Comments from the latest code review of the backend code
+    #[serde(default)]
+    pub review_comments: Option<Vec<ReviewComment>>,
+    /// Static HTML/CSS/JS pages served by the web server (relative to its static directory)
+    #[serde(default)]
+    pub frontend_files: Option<Vec<ProjectFile>>,
+    /// Entities, fields and relations the backend stores
+    #[serde(default)]
+    pub data_model: Option<DataModel>,
+    /// Files of the backend project with paths relative to the web server project
+    #[serde(default)]
+    pub backend_files: Option<Vec<ProjectFile>>,
+    /// Findings from the latest security scan of the backend code
+    #[serde(default)]
+    pub security_findings: Option<Vec<SecurityFinding>>,
+    /// Requirements clarified with the user before any work started
+    #[serde(default)]
+    pub requirements: Option<Vec<Requirement>>,
+    /// Integration tests written for the web server (tests/api_tests.rs)
+    #[serde(default)]
+    pub integration_tests: Option<String>,
+    /// Set when adding authentication failed, so protected routes are not probed as a logged in user
+    #[serde(default)]
+    pub is_auth_incomplete: bool,
+}
+
+/// Represents the errors an agent can return from its execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentError {
+    /// The run was cancelled (i.e. Ctrl-C) while the agent was working
+    Cancelled,
+    /// The user chose to abort the project at an approval gate
+    Aborted(String),
+    /// The agent could not complete its work
+    Failed(String),
+    /// The agent needs an earlier agent to redo part of its work
+    Escalation(Escalation),
+}
+
+/// Represents a request from one agent for an earlier agent to redo part of its work
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Escalation {
+    /// Position of the agent being asked to redo its work
+    pub target_position: String,
+    /// Why the work needs to be redone
+    pub reason: String,
+    /// External URLs that could not be used and should be replaced
+    pub rejected_urls: Vec<String>,
+}
+
+impl Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "Agent execution was cancelled"),
+            Self::Aborted(gate) => write!(f, "Project aborted by the user at the '{}' gate", gate),
+            Self::Failed(reason) => write!(f, "Agent failed: {}", reason),
+            Self::Escalation(escalation) => write!(
+                f,
+                "Escalated to {}: {}",
+                escalation.target_position, escalation.reason
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+/// A response from the LLM that does not match the expected structure fails the agent
+impl From<serde_json::Error> for AgentError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Failed(format!("Unable to read JSON: {}", e))
+    }
 }
 
 /// Represents special traits that an agent will have
@@ -52,9 +410,133 @@ pub trait SpecialFunctions: Debug {
     // Used so that manager can get attributes from Agents
     fn get_attributes_from_agent(&self) -> &BasicAgent;
 
+    // Used so that manager can reset an agent when it needs to run again
+    fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent;
+
+    // Used by the manager when a later agent escalates a problem back to this agent
+    // By default the agent simply runs again from the start
+    fn receive_escalation(&mut self, _escalation: &Escalation) {
+        self.get_attributes_from_agent_mut()
+            .update_state(AgentState::Discovery);
+    }
+
+    // Used by the manager when a review finds requirements that this agent still needs to build
+    // By default the agent simply runs again from the start
+    fn receive_follow_up(&mut self, _tasks: &[String]) {
+        self.get_attributes_from_agent_mut()
+            .update_state(AgentState::Discovery);
+    }
+
+    // Used by the manager to fill in safe defaults when this agent is skipped after failing
+    fn fill_defaults(&self, _factsheet: &mut FactSheet) {}
+
+    // Used by the manager to run a finished agent again when the work it depends on has changed
+    // By default a finished agent stays finished
+    fn is_outdated(&self, _factsheet: &FactSheet) -> bool {
+        false
+    }
+
     // This function will allow agents to execute their logic
+    // The cancellation token is triggered when the user asks to stop the run
+    // Agents pass it to anything that waits (LLM calls, prompts, cargo) rather than checking it between states,
+    // as the manager stops before each agent and drops the agent's work once the run is cancelled
     async fn execute(
         &mut self,
         factsheet: &mut FactSheet,
+        cancel: &CancellationToken,
     ) -> Result<(), Box<dyn std::error::Error>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_route_object_protected_flag() {
+        let routes: Vec<RouteObject> = serde_json::from_str(
+            r#"[
+              { "route": "/item", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None", "is_protected": "true" },
+              { "route": "/login", "is_route_dynamic": "false", "method": "post", "request_body": "None", "response": "None", "is_protected": false },
+              { "route": "/health", "is_route_dynamic": "false", "method": "get", "request_body": "None", "response": "None" }
+            ]"#,
+        )
+        .unwrap();
+
+        let flags: Vec<bool> = routes.iter().map(|route| route.is_protected).collect();
+        assert_eq!(flags, vec![true, false, false]);
+    }
+
+    #[test]
+    fn tests_toggle_project_scope_flag() {
+        let mut project_scope: ProjectScope = ProjectScope {
+            is_crud_required: true,
+            is_user_login_and_logout: false,
+            is_external_urls_required: false,
+            ..Default::default()
+        };
+
+        assert!(project_scope.toggle_flag("crud"));
+        assert!(project_scope.toggle_flag(" Login "));
+        assert!(!project_scope.toggle_flag("payments"));
+        assert!(!project_scope.is_crud_required);
+        assert!(project_scope.is_user_login_and_logout);
+        assert!(!project_scope.is_external_urls_required);
+
+        assert!(project_scope.toggle_flag("persistence=sqlite"));
+        assert!(project_scope.toggle_flag("realtime = SSE"));
+        assert!(!project_scope.toggle_flag("realtime=polling"));
+        assert!(project_scope.toggle_flag("admin"));
+        assert_eq!(project_scope.persistence, PersistenceType::Sqlite);
+        assert_eq!(project_scope.realtime, RealtimeType::Sse);
+        assert!(project_scope.is_auth_required());
+    }
+
+    #[test]
+    fn tests_project_scope_defaults_missing_fields() {
+        // A scope saved before the typed requirements were added
+        let project_scope: ProjectScope = serde_json::from_str(
+            r#"{ "is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false }"#,
+        )
+        .unwrap();
+        assert_eq!(project_scope.persistence, PersistenceType::JsonFile);
+        assert_eq!(project_scope.realtime, RealtimeType::None);
+        assert!(!project_scope.is_pagination_required);
+
+        let project_scope: ProjectScope = serde_json::from_str(
+            r#"{ "is_crud_required": true, "is_user_login_and_logout": false, "is_external_urls_required": false,
+                 "persistence": "sqlite", "realtime": "websocket", "is_pagination_required": "true" }"#,
+        )
+        .unwrap();
+        assert_eq!(project_scope.persistence, PersistenceType::Sqlite);
+        assert_eq!(project_scope.realtime, RealtimeType::Websockets);
+        assert!(project_scope.is_pagination_required);
+    }
+
+    #[test]
+    fn tests_project_scope_choices_ignore_case() {
+        let choices = |persistence: &str, realtime: &str| -> serde_json::Result<ProjectScope> {
+            serde_json::from_value(serde_json::json!({
+                "is_crud_required": true,
+                "is_user_login_and_logout": false,
+                "is_external_urls_required": false,
+                "persistence": persistence,
+                "realtime": realtime,
+            }))
+        };
+
+        let project_scope: ProjectScope = choices("SQLite", "WebSockets").unwrap();
+        assert_eq!(project_scope.persistence, PersistenceType::Sqlite);
+        assert_eq!(project_scope.realtime, RealtimeType::Websockets);
+
+        let project_scope: ProjectScope = choices("JSON_file", " SSE ").unwrap();
+        assert_eq!(project_scope.persistence, PersistenceType::JsonFile);
+        assert_eq!(project_scope.realtime, RealtimeType::Sse);
+
+        let project_scope: ProjectScope = choices("Json File", "None").unwrap();
+        assert_eq!(project_scope.persistence, PersistenceType::JsonFile);
+        assert_eq!(project_scope.realtime, RealtimeType::None);
+
+        // Unknown choices are still rejected rather than quietly replaced by the default
+        assert!(choices("Postgres", "none").is_err());
+    }
+}
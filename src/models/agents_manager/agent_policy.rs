@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+/// Describes what the manager does once an agent has used up its retries
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum FailureAction {
+    /// Fill in safe defaults for the agent's work and move on to the next agent
+    Skip,
+    /// Stop the whole project
+    Abort,
+}
+
+/// Represents how the manager handles an agent that fails
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub struct AgentPolicy {
+    /// Number of times the agent is run again after failing
+    pub max_retries: u8,
+    /// What to do once all retries have failed
+    pub on_failure: FailureAction,
+}
+
+/// Represents the action the manager takes after an agent has run
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub enum DecisionAction {
+    /// The agent finished its work
+    Completed,
+    /// The agent is run again from the start
+    Retry,
+    /// The agent was skipped and defaults were used for its work
+    Skip,
+    /// The project was stopped
+    Abort,
+    /// An earlier agent was asked to redo part of its work
+    Escalate(String),
+    /// An agent was sent follow-up tasks for requirements that are still missing
+    FollowUp(String),
+    /// The project was delivered with requirements still missing
+    Incomplete,
+}
+
+/// Represents a single decision made by the manager, kept for the final report
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ManagerDecision {
+    /// Position of the agent the decision is about
+    pub agent_position: String,
+    /// Which attempt of the agent this was (starting at 1)
+    pub attempt: u8,
+    /// The action taken
+    pub action: DecisionAction,
+    /// Why the action was taken
+    pub reason: String,
+}
+
+/// Implementation of AgentPolicy
+impl AgentPolicy {
+    /// Decide what to do after an agent has failed
+    /// # Arguments
+    ///
+    /// * `attempt` - The attempt that just failed (starting at 1)
+    pub fn decide(&self, attempt: u8) -> DecisionAction {
+        if attempt <= self.max_retries {
+            return DecisionAction::Retry;
+        }
+
+        match self.on_failure {
+            FailureAction::Skip => DecisionAction::Skip,
+            FailureAction::Abort => DecisionAction::Abort,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_policy_retries_before_failure_action() {
+        let policy: AgentPolicy = AgentPolicy {
+            max_retries: 2,
+            on_failure: FailureAction::Skip,
+        };
+
+        assert_eq!(policy.decide(1), DecisionAction::Retry);
+        assert_eq!(policy.decide(2), DecisionAction::Retry);
+        assert_eq!(policy.decide(3), DecisionAction::Skip);
+
+        let policy: AgentPolicy = AgentPolicy {
+            max_retries: 0,
+            on_failure: FailureAction::Abort,
+        };
+        assert_eq!(policy.decide(1), DecisionAction::Abort);
+    }
+}
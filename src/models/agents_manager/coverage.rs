@@ -0,0 +1,176 @@
+use crate::helpers::scope_features::active_features;
+use crate::helpers::stub_server::external_base_url_env_var;
+use crate::models::agents::agent_traits::{FactSheet, ProjectScope, RouteObject};
+use serde::{Deserialize, Serialize};
+
+/// Represents the verdict of the LLM judge on whether the project meets the user's request
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CoverageVerdict {
+    /// Flag to determine if every requirement has been met
+    pub is_satisfied: bool,
+    /// Requirements from the user's request that are missing from the project
+    pub missing_requirements: Vec<String>,
+}
+
+/// Check if any route uses one of the given methods
+fn has_route_with_method(routes: &[RouteObject], methods: &[&str]) -> bool {
+    routes
+        .iter()
+        .any(|route| methods.contains(&route.method.to_lowercase().as_str()))
+}
+
+/// Check if any route path contains the given keyword
+fn has_route_containing(routes: &[RouteObject], keyword: &str) -> bool {
+    routes
+        .iter()
+        .any(|route| route.route.to_lowercase().contains(keyword))
+}
+
+/// Run deterministic checks of the produced API and code against the project scope
+/// Returns a description of every requirement that is missing
+pub fn check_requirement_coverage(factsheet: &FactSheet) -> Vec<String> {
+    let mut missing: Vec<String> = vec![];
+
+    let backend_code: &str = match &factsheet.backend_code {
+        Some(code) => code.as_str(),
+        None => return vec!["No backend code has been written".to_string()],
+    };
+
+    let routes: &[RouteObject] = match &factsheet.api_endpoint_schema {
+        Some(routes) => routes.as_slice(),
+        None => return vec!["No API endpoint schema has been extracted".to_string()],
+    };
+
+    let project_scope: ProjectScope = match factsheet.project_scope {
+        Some(project_scope) => project_scope,
+        None => return missing,
+    };
+
+    // CRUD needs routes to create, read, update and delete items
+    if project_scope.is_crud_required {
+        let crud_methods: [(&str, &[&str]); 4] = [
+            ("create (POST)", &["post"]),
+            ("read (GET)", &["get"]),
+            ("update (PUT or PATCH)", &["put", "patch"]),
+            ("delete (DELETE)", &["delete"]),
+        ];
+        for (operation, methods) in crud_methods {
+            if !has_route_with_method(routes, methods) {
+                missing.push(format!(
+                    "CRUD is required but there is no {} route",
+                    operation
+                ));
+            }
+        }
+    }
+
+    // Login and logout need their own routes
+    if project_scope.is_user_login_and_logout {
+        for keyword in ["login", "logout"] {
+            if !has_route_containing(routes, keyword) {
+                missing.push(format!(
+                    "User login and logout is required but there is no {} route",
+                    keyword
+                ));
+            }
+        }
+    }
+
+    // External data needs at least one of the approved URLs to be called,
+    // either directly or through the environment variable holding the base URL of its host
+    if project_scope.is_external_urls_required {
+        let urls: &[String] = factsheet.external_urls.as_deref().unwrap_or_default();
+        let is_url_called = |url: &String| {
+            backend_code.contains(url.as_str())
+                || external_base_url_env_var(url)
+                    .is_some_and(|env_var| backend_code.contains(env_var.as_str()))
+        };
+        if !urls.iter().any(is_url_called) {
+            missing.push(
+                "External data is required but the backend does not call any approved external URL"
+                    .to_string(),
+            );
+        }
+    }
+
+    // Every feature in the scope leaves a trace in the code (i.e. per_page for pagination)
+    for feature in active_features(&project_scope) {
+        if !feature.is_built(backend_code) {
+            missing.push(format!(
+                "The scope requires {} but the backend does not implement it: {}",
+                feature.name, feature.backend_instruction
+            ));
+        }
+    }
+
+    // Frontend pages need to be served by the web server
+    let has_frontend: bool = factsheet
+        .frontend_files
+        .as_ref()
+        .is_some_and(|files| !files.is_empty());
+    let serves_frontend: bool = routes
+        .iter()
+        .any(|route| route.route == "/" && route.method.to_lowercase() == "get");
+    if has_frontend && !serves_frontend {
+        missing.push(
+            "Frontend pages exist in the static directory but are not served: add GET / returning static/index.html and GET /static/{filename} returning files from the static directory"
+                .to_string(),
+        );
+    }
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_check_requirement_coverage() {
+        let mut factsheet: FactSheet = FactSheet {
+            project_description: "build a todo website with login".to_string(),
+            project_scope: Some(ProjectScope {
+                is_crud_required: true,
+                is_user_login_and_logout: true,
+                is_external_urls_required: true,
+                ..Default::default()
+            }),
+            external_urls: Some(vec!["https://api.example.com/time".to_string()]),
+            backend_code: Some("fn main() {}".to_string()),
+            api_endpoint_schema: Some(vec![
+                RouteObject::for_test("post", "/item"),
+                RouteObject::for_test("get", "/item/{id}"),
+                RouteObject::for_test("post", "/login"),
+            ]),
+            ..Default::default()
+        };
+
+        let missing: Vec<String> = check_requirement_coverage(&factsheet);
+        assert_eq!(missing.len(), 4);
+        assert!(missing[0].contains("update"));
+        assert!(missing[1].contains("delete"));
+        assert!(missing[2].contains("logout"));
+        assert!(missing[3].contains("external URL"));
+
+        factsheet.backend_code = Some(
+            "env::var(\"EXTERNAL_API_EXAMPLE_COM_BASE_URL\").unwrap_or(\"https://api.example.com\".to_string()) + \"/time\""
+                .to_string(),
+        );
+        factsheet.api_endpoint_schema.as_mut().unwrap().extend([
+            RouteObject::for_test("put", "/item/{id}"),
+            RouteObject::for_test("delete", "/item/{id}"),
+            RouteObject::for_test("post", "/logout"),
+        ]);
+        assert!(check_requirement_coverage(&factsheet).is_empty());
+
+        // Turning on pagination needs the page size to be read
+        factsheet
+            .project_scope
+            .as_mut()
+            .unwrap()
+            .is_pagination_required = true;
+        let missing: Vec<String> = check_requirement_coverage(&factsheet);
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].contains("pagination"));
+    }
+}
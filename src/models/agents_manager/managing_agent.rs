@@ -1,25 +1,72 @@
 use crate::models::agent_basic::basic_agent::{AgentState, BasicAgent};
-use crate::models::agents::agent_traits::{FactSheet, SpecialFunctions};
+use crate::models::agent_basic::basic_traits::BasicTraits;
+use crate::models::agents::agent_traits::{AgentError, Escalation, FactSheet, SpecialFunctions};
+use crate::models::agents_manager::agent_policy::{
+    AgentPolicy, DecisionAction, FailureAction, ManagerDecision,
+};
+use crate::models::agents_manager::coverage::{check_requirement_coverage, CoverageVerdict};
 
-use crate::ai_functions::aifunc_managing::convert_user_input_to_goal;
-use crate::helpers::general::ai_task_request;
+use crate::ai_functions::aifunc_managing::{
+    convert_user_input_to_goal, print_requirement_coverage,
+};
+use crate::helpers::command_line::{
+    request_approval, ApprovalDecision, ApprovalGate, PrintCommand,
+};
+use crate::helpers::general::{
+    ai_task_request, ai_task_request_decoded, save_checkpoint, save_run_artifact,
+};
+use crate::models::agents::agent_analyst::AgentRequirementsAnalyst;
 use crate::models::agents::agent_architect::AgentSolutionArchitect;
 use crate::models::agents::agent_backend::AgentBackendDeveloper;
+use crate::models::agents::agent_database::AgentDatabaseDesigner;
+use crate::models::agents::agent_docs::AgentTechnicalWriter;
+use crate::models::agents::agent_frontend::AgentFrontendDeveloper;
+use crate::models::agents::agent_tester::AgentIntegrationTester;
+
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+/// Maximum number of times agents can escalate problems back to earlier agents in a single run
+const MAX_ESCALATIONS: u8 = 2;
+
+/// Maximum number of times the manager sends follow-up tasks after reviewing the project
+const MAX_FOLLOW_UP_ROUNDS: u8 = 2;
+
+/// Position of the agent that builds missing requirements found in a review
+const FOLLOW_UP_AGENT_POSITION: &str = "Backend Developer";
+
+/// Represents the final report written at the end of a run
+#[derive(Debug, Serialize)]
+struct ProjectReport<'a> {
+    /// The factsheet as it was left by the agents
+    factsheet: &'a FactSheet,
+    /// Every decision made by the manager during the run
+    decisions: &'a [ManagerDecision],
+}
 
 /// Represents a managing agent
 #[derive(Debug)]
 pub struct ManagingAgent {
     /// Attributes belonging to the agent
     _attributes: BasicAgent,
+    /// The original request made by the user
+    user_request: String,
     /// Fact sheet for the agent
     factsheet: FactSheet,
     /// A group of agents that have access to special functions
     agents: Vec<Box<dyn SpecialFunctions>>,
+    /// The failure policy for each agent (in the same order as the agents)
+    policies: Vec<AgentPolicy>,
+    /// Decisions made while running the agents
+    decisions: Vec<ManagerDecision>,
 }
 
 // TODO: Add ManagingAgent implementation
 impl ManagingAgent {
-    pub async fn new(usr_req: String) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(
+        usr_req: String,
+        cancel: &CancellationToken,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let position: String = "Project Manager".to_string();
 
         let attributes: BasicAgent = BasicAgent {
@@ -30,13 +77,44 @@ impl ManagingAgent {
             memory: vec![],
         };
 
-        let project_description: String = ai_task_request(
-            usr_req,
+        let mut project_description: String = ai_task_request(
+            usr_req.clone(),
             &position,
             get_function_string!(convert_user_input_to_goal),
             convert_user_input_to_goal,
+            cancel,
         )
-        .await;
+        .await?;
+
+        // Let the user review the goal before any agent starts working on it
+        loop {
+            match request_approval(
+                ApprovalGate::ProjectGoal,
+                &position,
+                &project_description,
+                cancel,
+            )
+            .await?
+            {
+                ApprovalDecision::Approve => break,
+                ApprovalDecision::Edit(goal) => project_description = goal,
+                ApprovalDecision::Regenerate => {
+                    project_description = ai_task_request(
+                        usr_req.clone(),
+                        &position,
+                        get_function_string!(convert_user_input_to_goal),
+                        convert_user_input_to_goal,
+                        cancel,
+                    )
+                    .await?
+                }
+                ApprovalDecision::Abort => {
+                    return Err(Box::new(AgentError::Aborted(
+                        ApprovalGate::ProjectGoal.key().to_string(),
+                    )))
+                }
+            }
+        }
 
         let agents: Vec<Box<dyn SpecialFunctions>> = vec![];
 
@@ -46,54 +124,671 @@ impl ManagingAgent {
             external_urls: None,
             backend_code: None,
             api_endpoint_schema: None,
+            ..Default::default()
         };
 
         Ok(Self {
             _attributes: attributes,
+            user_request: usr_req,
             factsheet,
             agents,
+            policies: vec![],
+            decisions: vec![],
         })
     }
 
-    /// Add a new agent
-    fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>) {
+    /// Add a new agent along with the policy used when it fails
+    fn add_agent(&mut self, agent: Box<dyn SpecialFunctions>, policy: AgentPolicy) {
         self.agents.push(agent);
+        self.policies.push(policy);
     }
 
     /// Create all required agents
     fn create_agents(&mut self) {
-        self.add_agent(Box::new(AgentSolutionArchitect::new()));
-        self.add_agent(Box::new(AgentBackendDeveloper::new()));
+        // Later agents make their own assumptions if the request cannot be clarified
+        self.add_agent(
+            Box::new(AgentRequirementsAnalyst::new(self.user_request.clone())),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        // Without a scope or URLs the backend can still build a plain CRUD site
+        self.add_agent(
+            Box::new(AgentSolutionArchitect::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        // Without a data model the backend designs its own structs
+        self.add_agent(
+            Box::new(AgentDatabaseDesigner::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        self.add_agent(
+            Box::new(AgentBackendDeveloper::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Abort,
+            },
+        );
+        // Failing tests are sent back to the backend, so the tester itself is only skipped
+        self.add_agent(
+            Box::new(AgentIntegrationTester::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        // The API is still usable without pages, so a failing frontend is skipped
+        self.add_agent(
+            Box::new(AgentFrontendDeveloper::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        // Missing docs never stop the project from being delivered
+        self.add_agent(
+            Box::new(AgentTechnicalWriter::new()),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+    }
+
+    /// Position of the agent at the given index
+    fn agent_position(&self, index: usize) -> String {
+        self.agents[index]
+            .get_attributes_from_agent()
+            .position
+            .clone()
+    }
+
+    /// Record a decision so it appears in the final report
+    fn record_decision(
+        &mut self,
+        agent_position: String,
+        attempt: u8,
+        action: DecisionAction,
+        reason: String,
+    ) {
+        let decision: ManagerDecision = ManagerDecision {
+            agent_position,
+            attempt,
+            action,
+            reason,
+        };
+
+        let decision_msg: String = format!(
+            "{} (attempt {}): {:?} - {}",
+            decision.agent_position, decision.attempt, decision.action, decision.reason
+        );
+        PrintCommand::UnitTest.print_agent_message(&self._attributes.position, &decision_msg);
+
+        self.decisions.push(decision);
     }
 
     /// Start the project
-    pub async fn execute_project(&mut self) {
+    /// Returns an error if the run was cancelled or aborted before all agents finished
+    pub async fn execute_project(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         self.create_agents();
+        self.run_agents(cancel).await?;
+        self.review_project(cancel).await?;
+
+        self.save_report();
+        self.print_completion_summary();
+        Ok(())
+    }
+
+    /// Compare what was built against the user's request and send agents back to build anything missing
+    async fn review_project(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let position: String = self._attributes.position.clone();
+
+        for round in 1..=MAX_FOLLOW_UP_ROUNDS + 1 {
+            let missing_requirements: Vec<String> =
+                match self.find_missing_requirements(cancel).await {
+                    Ok(missing_requirements) => missing_requirements,
+                    Err(e) => {
+                        self.print_cancellation_summary(&position);
+                        return Err(Box::new(e));
+                    }
+                };
+
+            if missing_requirements.is_empty() {
+                self.record_decision(
+                    position,
+                    round,
+                    DecisionAction::Completed,
+                    "Every requirement is covered".to_string(),
+                );
+                return Ok(());
+            }
+
+            let missing_str: String = missing_requirements.join("; ");
+            if round > MAX_FOLLOW_UP_ROUNDS {
+                self.record_decision(position, round, DecisionAction::Incomplete, missing_str);
+                return Ok(());
+            }
 
-        for agent in &mut self.agents {
-            let _agent_res: Result<(), Box<dyn std::error::Error>> =
-                agent.execute(&mut self.factsheet).await;
+            // Send the missing requirements to the agent that builds them, then run it again
+            let target: Option<usize> = self.agents.iter().position(|agent| {
+                agent.get_attributes_from_agent().position == FOLLOW_UP_AGENT_POSITION
+            });
+            let target: usize = match target {
+                Some(target) => target,
+                None => {
+                    self.record_decision(position, round, DecisionAction::Incomplete, missing_str);
+                    return Ok(());
+                }
+            };
+
+            self.record_decision(
+                position.clone(),
+                round,
+                DecisionAction::FollowUp(self.agent_position(target)),
+                missing_str,
+            );
+            self.agents[target].receive_follow_up(&missing_requirements);
+            self.run_agents(cancel).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Combine deterministic checks with an LLM judge to find requirements that are still missing
+    /// Returns AgentError::Cancelled if the run is cancelled while the judge is working
+    async fn find_missing_requirements(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<String>, AgentError> {
+        let mut missing_requirements: Vec<String> = check_requirement_coverage(&self.factsheet);
+
+        let msg_context: String = format!(
+            "USER_REQUEST: {} \n PROJECT_DESCRIPTION: {} \n PROJECT_SCOPE: {:?} \n API_ENDPOINT_SCHEMA: {:?} \n BACKEND_CODE: {:?} \n",
+            self.user_request,
+            self.factsheet.project_description,
+            self.factsheet.project_scope,
+            self.factsheet.api_endpoint_schema,
+            self.factsheet.backend_code
+        );
+
+        let verdict: Result<CoverageVerdict, AgentError> = ai_task_request_decoded(
+            msg_context,
+            &self._attributes.position,
+            get_function_string!(print_requirement_coverage),
+            print_requirement_coverage,
+            cancel,
+        )
+        .await;
+
+        // The deterministic checks still apply if the judge's response cannot be read
+        match verdict {
+            Ok(verdict) if !verdict.is_satisfied => {
+                missing_requirements.extend(verdict.missing_requirements)
+            }
+            Ok(_) => {}
+            Err(AgentError::Cancelled) => return Err(AgentError::Cancelled),
+            Err(e) => {
+                let err_msg: String = format!("Unable to read requirement coverage verdict: {}", e);
+                PrintCommand::Issue.print_agent_message(&self._attributes.position, &err_msg);
+            }
+        }
+
+        Ok(missing_requirements)
+    }
+
+    /// Run every agent in order, applying failure policies and escalations
+    async fn run_agents(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut attempts: Vec<u8> = vec![0; self.agents.len()];
+        let mut escalations: u8 = 0;
+        let mut index: usize = 0;
+
+        while index < self.agents.len() {
+            // Stop before starting any new work if the run has been cancelled
+            if cancel.is_cancelled() {
+                let next_agent: String = self.agent_position(index);
+                self.print_cancellation_summary(&next_agent);
+                return Err(Box::new(AgentError::Cancelled));
+            }
+
+            // Agents that have already finished only run again if the manager resets them
+            // or the work they depend on has changed since they last ran
+            if self.agents[index].get_attributes_from_agent().state == AgentState::Finished {
+                if !self.agents[index].is_outdated(&self.factsheet) {
+                    index += 1;
+                    continue;
+                }
+                self.agents[index]
+                    .get_attributes_from_agent_mut()
+                    .update_state(AgentState::Discovery);
+            }
+
+            attempts[index] += 1;
+            let attempt: u8 = attempts[index];
+
+            // LLM calls stop on the token themselves, and dropping the agent future kills child processes
+            let agent: &mut Box<dyn SpecialFunctions> = &mut self.agents[index];
+            let agent_res: Result<(), Box<dyn std::error::Error>> = tokio::select! {
+                res = agent.execute(&mut self.factsheet, cancel) => res,
+                _ = cancel.cancelled() => Err(Box::new(AgentError::Cancelled)),
+            };
 
             // Used for debugging agent attributes
             let agent_info: &BasicAgent = agent.get_attributes_from_agent();
             dbg!(agent_info);
+
+            if cancel.is_cancelled() {
+                let interrupted: String = agent_info.position.clone();
+                self.print_cancellation_summary(&interrupted);
+                return Err(Box::new(AgentError::Cancelled));
+            }
+
+            let error: Box<dyn std::error::Error> = match agent_res {
+                Ok(()) => {
+                    self.record_decision(
+                        self.agent_position(index),
+                        attempt,
+                        DecisionAction::Completed,
+                        "Finished".to_string(),
+                    );
+                    index += 1;
+                    continue;
+                }
+                Err(e) => e,
+            };
+
+            match error.downcast_ref::<AgentError>() {
+                // Stop the project if the user aborted it at an approval gate
+                Some(AgentError::Aborted(gate)) => {
+                    let gate: String = gate.clone();
+                    self.record_decision(
+                        self.agent_position(index),
+                        attempt,
+                        DecisionAction::Abort,
+                        error.to_string(),
+                    );
+                    self.save_report();
+                    return Err(Box::new(AgentError::Aborted(gate)));
+                }
+
+                // Send the problem back to the earlier agent, then run every agent after it again
+                Some(AgentError::Escalation(escalation)) => {
+                    let target: Option<usize> = self.agents[..index].iter().position(|agent| {
+                        agent.get_attributes_from_agent().position == escalation.target_position
+                    });
+
+                    if let (Some(target), true) = (target, escalations < MAX_ESCALATIONS) {
+                        let escalation: Escalation = escalation.clone();
+                        escalations += 1;
+                        self.record_decision(
+                            self.agent_position(index),
+                            attempt,
+                            DecisionAction::Escalate(escalation.target_position.clone()),
+                            escalation.reason.clone(),
+                        );
+
+                        self.agents[target].receive_escalation(&escalation);
+                        for later in target + 1..=index {
+                            self.agents[later]
+                                .get_attributes_from_agent_mut()
+                                .update_state(AgentState::Discovery);
+                        }
+                        index = target;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            // Apply the agent's policy to any other failure
+            let reason: String = error.to_string();
+            let action: DecisionAction = self.policies[index].decide(attempt);
+            self.record_decision(
+                self.agent_position(index),
+                attempt,
+                action.clone(),
+                reason.clone(),
+            );
+
+            match action {
+                DecisionAction::Retry => {
+                    self.agents[index]
+                        .get_attributes_from_agent_mut()
+                        .update_state(AgentState::Discovery);
+                }
+                DecisionAction::Skip => {
+                    self.agents[index].fill_defaults(&mut self.factsheet);
+                    index += 1;
+                }
+                _ => {
+                    self.save_report();
+                    return Err(Box::new(AgentError::Failed(reason)));
+                }
+            }
         }
+
+        Ok(())
+    }
+
+    /// Save the factsheet and every decision made by the manager as the final report
+    fn save_report(&self) {
+        let report: ProjectReport = ProjectReport {
+            factsheet: &self.factsheet,
+            decisions: &self.decisions,
+        };
+
+        let report_msg: String = match save_run_artifact("report.json", &report) {
+            Ok(path) => format!("Report written to {}", path.display()),
+            Err(e) => format!("Failed to write report: {}", e),
+        };
+        PrintCommand::UnitTest.print_agent_message(&self._attributes.position, &report_msg);
+    }
+
+    /// Print the state every agent ended in
+    fn print_agent_states(&self, command: PrintCommand) {
+        for agent in &self.agents {
+            let agent_info: &BasicAgent = agent.get_attributes_from_agent();
+            let state_msg: String = format!("{}: {:?}", agent_info.position, agent_info.state);
+            command.print_agent_message(&self._attributes.position, state_msg.as_str());
+        }
+    }
+
+    /// Summarise how the run ended once every agent has finished
+    fn print_completion_summary(&self) {
+        let finished_msg: String = format!(
+            "Project finished with {} manager decision(s)",
+            self.decisions.len()
+        );
+        PrintCommand::UnitTest.print_agent_message(&self._attributes.position, &finished_msg);
+        self.print_agent_states(PrintCommand::UnitTest);
+    }
+
+    /// Write a checkpoint of the factsheet and summarise what was completed before cancellation
+    fn print_cancellation_summary(&self, interrupted_agent: &str) {
+        let position: &str = self._attributes.position.as_str();
+
+        let interrupted_msg: String =
+            format!("Run cancelled while '{}' was working", interrupted_agent);
+        PrintCommand::Issue.print_agent_message(position, interrupted_msg.as_str());
+        self.print_agent_states(PrintCommand::Issue);
+
+        let checkpoint_msg: String = match save_checkpoint(&self.factsheet) {
+            Ok(path) => format!("Checkpoint written to {}", path.display()),
+            Err(e) => format!("Failed to write checkpoint: {}", e),
+        };
+        PrintCommand::Issue.print_agent_message(position, checkpoint_msg.as_str());
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::helpers::general::queue_llm_response;
+    use crate::helpers::stub_server::{StubServer, UrlSnapshot};
+    use crate::models::agents::agent_traits::{ProjectScope, UrlProfile};
+    use async_trait::async_trait;
+
+    /// URL that agents escalating in tests could not use
+    const REJECTED_URL: &str = "https://api.broken.com/v1/rates";
+
+    /// Agent that fails a set number of times before finishing
+    #[derive(Debug)]
+    struct FlakyAgent {
+        attributes: BasicAgent,
+        failures_left: u8,
+        escalate_to: Option<String>,
+    }
+
+    impl FlakyAgent {
+        fn new(position: &str, failures_left: u8, escalate_to: Option<&str>) -> Self {
+            Self {
+                attributes: BasicAgent::new("Testing".to_string(), position.to_string()),
+                failures_left,
+                escalate_to: escalate_to.map(|target| target.to_string()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SpecialFunctions for FlakyAgent {
+        fn get_attributes_from_agent(&self) -> &BasicAgent {
+            &self.attributes
+        }
+
+        fn get_attributes_from_agent_mut(&mut self) -> &mut BasicAgent {
+            &mut self.attributes
+        }
+
+        async fn execute(
+            &mut self,
+            _factsheet: &mut FactSheet,
+            _cancel: &CancellationToken,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            if self.failures_left > 0 {
+                self.failures_left -= 1;
+                return Err(Box::new(match &self.escalate_to {
+                    Some(target) => AgentError::Escalation(Escalation {
+                        target_position: target.clone(),
+                        reason: "Bad URLs".to_string(),
+                        rejected_urls: vec![REJECTED_URL.to_string()],
+                    }),
+                    None => AgentError::Failed("Flaky".to_string()),
+                }));
+            }
+            self.attributes.update_state(AgentState::Finished);
+            Ok(())
+        }
+    }
+
+    fn test_manager() -> ManagingAgent {
+        ManagingAgent {
+            _attributes: BasicAgent::new("Testing".to_string(), "Project Manager".to_string()),
+            user_request: "build me a website".to_string(),
+            factsheet: FactSheet {
+                project_description: "build a website".to_string(),
+                project_scope: None,
+                external_urls: None,
+                backend_code: None,
+                api_endpoint_schema: None,
+                ..Default::default()
+            },
+            agents: vec![],
+            policies: vec![],
+            decisions: vec![],
+        }
+    }
+
+    fn actions(manager: &ManagingAgent) -> Vec<(String, DecisionAction)> {
+        manager
+            .decisions
+            .iter()
+            .map(|decision| (decision.agent_position.clone(), decision.action.clone()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tests_manager_applies_failure_policies() {
+        let mut manager: ManagingAgent = test_manager();
+        manager.add_agent(
+            Box::new(FlakyAgent::new("First", 2, None)),
+            AgentPolicy {
+                max_retries: 1,
+                on_failure: FailureAction::Skip,
+            },
+        );
+        manager.add_agent(
+            Box::new(FlakyAgent::new("Second", 1, None)),
+            AgentPolicy {
+                max_retries: 0,
+                on_failure: FailureAction::Abort,
+            },
+        );
+
+        let res = manager.run_agents(&CancellationToken::new()).await;
+
+        assert!(res.is_err());
+        assert_eq!(
+            actions(&manager),
+            vec![
+                ("First".to_string(), DecisionAction::Retry),
+                ("First".to_string(), DecisionAction::Skip),
+                ("Second".to_string(), DecisionAction::Abort),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_manager_stops_when_cancelled() {
+        let mut manager: ManagingAgent = test_manager();
+        let policy: AgentPolicy = AgentPolicy {
+            max_retries: 0,
+            on_failure: FailureAction::Abort,
+        };
+        manager.add_agent(Box::new(FlakyAgent::new("First", 0, None)), policy);
+
+        let cancel: CancellationToken = CancellationToken::new();
+        cancel.cancel();
+
+        let err: Box<dyn std::error::Error> = manager
+            .run_agents(&cancel)
+            .await
+            .expect_err("Cancelled run should not start any agent");
+        assert_eq!(
+            err.downcast_ref::<AgentError>(),
+            Some(&AgentError::Cancelled)
+        );
+        assert!(manager.decisions.is_empty());
+        assert_eq!(
+            manager.agents[0].get_attributes_from_agent().state,
+            AgentState::Discovery
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_manager_escalates_to_earlier_agent() {
+        let mut manager: ManagingAgent = test_manager();
+        let policy: AgentPolicy = AgentPolicy {
+            max_retries: 0,
+            on_failure: FailureAction::Abort,
+        };
+        manager.add_agent(Box::new(FlakyAgent::new("Architect", 0, None)), policy);
+        manager.add_agent(
+            Box::new(FlakyAgent::new("Backend", 1, Some("Architect"))),
+            policy,
+        );
+
+        manager
+            .run_agents(&CancellationToken::new())
+            .await
+            .expect("Escalation should recover");
+
+        assert_eq!(
+            actions(&manager),
+            vec![
+                ("Architect".to_string(), DecisionAction::Completed),
+                (
+                    "Backend".to_string(),
+                    DecisionAction::Escalate("Architect".to_string())
+                ),
+                ("Architect".to_string(), DecisionAction::Completed),
+                ("Backend".to_string(), DecisionAction::Completed),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tests_manager_escalation_replaces_rejected_urls() {
+        let stub_server: StubServer = StubServer::start(vec![UrlSnapshot {
+            url: "https://api.example.com/v1/rates".to_string(),
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: r#"{"rates": {"EUR": 0.92}}"#.to_string(),
+        }])
+        .await
+        .expect("Failed to start stub server");
+        let replacement_url: String = format!(
+            "http://127.0.0.1:{}/api.example.com/v1/rates",
+            stub_server.port()
+        );
+
+        let mut manager: ManagingAgent = test_manager();
+        manager.factsheet.project_scope = Some(ProjectScope {
+            is_crud_required: true,
+            is_user_login_and_logout: false,
+            is_external_urls_required: true,
+            ..Default::default()
+        });
+        manager.factsheet.external_urls = Some(vec![]);
+
+        // The Solutions Architect has already run, so only the escalation sends it back to work
+        let policy: AgentPolicy = AgentPolicy {
+            max_retries: 0,
+            on_failure: FailureAction::Abort,
+        };
+        let mut architect: AgentSolutionArchitect = AgentSolutionArchitect::new();
+        architect
+            .get_attributes_from_agent_mut()
+            .update_state(AgentState::Finished);
+        manager.add_agent(Box::new(architect), policy);
+        manager.add_agent(
+            Box::new(FlakyAgent::new(
+                "Backend Developer",
+                1,
+                Some("Solutions Architect"),
+            )),
+            policy,
+        );
+
+        queue_llm_response(&serde_json::to_string(&vec![&replacement_url]).unwrap());
+        manager
+            .run_agents(&CancellationToken::new())
+            .await
+            .expect("Escalation should recover");
+
+        assert_eq!(
+            actions(&manager),
+            vec![
+                (
+                    "Backend Developer".to_string(),
+                    DecisionAction::Escalate("Solutions Architect".to_string())
+                ),
+                ("Solutions Architect".to_string(), DecisionAction::Completed),
+                ("Backend Developer".to_string(), DecisionAction::Completed),
+            ]
+        );
+        assert_eq!(manager.factsheet.external_urls, Some(vec![replacement_url]));
+        let profiles: Vec<UrlProfile> = manager.factsheet.external_url_profiles.unwrap();
+        assert!(profiles.iter().all(|profile| profile.url != REJECTED_URL));
+    }
 
     #[tokio::test]
     async fn tests_managing_agent() {
         let usr_request: &str = "need a full stack app that fetches and tracks my fitness progress. Needs to include timezone info from the web.";
 
-        let mut managing_agent: ManagingAgent = ManagingAgent::new(usr_request.to_string())
-            .await
-            .expect("Error creating Managing Agent");
+        let mut managing_agent: ManagingAgent =
+            ManagingAgent::new(usr_request.to_string(), &CancellationToken::new())
+                .await
+                .expect("Error creating Managing Agent");
 
-        managing_agent.execute_project().await;
+        managing_agent
+            .execute_project(&CancellationToken::new())
+            .await
+            .expect("Project was cancelled");
 
         dbg!(managing_agent.factsheet);
     }
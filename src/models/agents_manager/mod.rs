@@ -1 +1,3 @@
+pub mod agent_policy;
+pub mod coverage;
 pub mod managing_agent;
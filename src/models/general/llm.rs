@@ -39,4 +39,4 @@ pub struct APIChoice {
 pub struct APIResponse {
     // The choices from the response
     pub choices: Vec<APIChoice>,
-}
\ No newline at end of file
+}